@@ -12,7 +12,7 @@ async fn main() {
         access_token: Some("abcdefg".to_string()),
         ..Default::default()
     };
-    let http_conn = HttpConnect::new(config);
+    let http_conn = HttpConnect::new(config).unwrap();
     let payload = ApiPayload::SendGroupForwardMsg(SendGroupForwardMsg {
         group_id: 418012707,
         messages: vec![
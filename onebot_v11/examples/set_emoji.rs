@@ -63,7 +63,7 @@ async fn main() {
     let ws_conn = ReverseWsConnect::new(ws_reverse_config).await.unwrap();
     let mut subscriber = ws_conn.subscribe().await;
 
-    while let Ok(event) = subscriber.recv().await {
+    while let Some(event) = subscriber.recv().await {
         if let Event::Message(
             Message::PrivateMessage(PrivateMessage {
                 message_id,
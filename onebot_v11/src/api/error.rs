@@ -0,0 +1,204 @@
+use std::fmt;
+use std::time::Duration;
+
+/// OneBot 实现返回的 `retcode`, 常见的返回码对应具名变体
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RetCode {
+    /// 100, 参数缺失或参数无效
+    InvalidParams,
+    /// 102, 返回数据无效
+    InvalidData,
+    /// 103, 操作失败
+    OperationFailed,
+    /// 104, 凭证过期
+    CredentialsExpired,
+    /// 201, 工作线程池未正确初始化
+    WorkerNotReady,
+    /// 1400, 请求格式错误
+    BadRequest,
+    /// 1401, 未提供 access token
+    Unauthorized,
+    /// 1403, access token 不符合
+    Forbidden,
+    /// 1404, API 不存在
+    ApiNotFound,
+    Other(i64),
+}
+
+impl RetCode {
+    pub fn code(&self) -> i64 {
+        match self {
+            RetCode::InvalidParams => 100,
+            RetCode::InvalidData => 102,
+            RetCode::OperationFailed => 103,
+            RetCode::CredentialsExpired => 104,
+            RetCode::WorkerNotReady => 201,
+            RetCode::BadRequest => 1400,
+            RetCode::Unauthorized => 1401,
+            RetCode::Forbidden => 1403,
+            RetCode::ApiNotFound => 1404,
+            RetCode::Other(code) => *code,
+        }
+    }
+}
+
+impl From<i64> for RetCode {
+    fn from(code: i64) -> Self {
+        match code {
+            100 => RetCode::InvalidParams,
+            102 => RetCode::InvalidData,
+            103 => RetCode::OperationFailed,
+            104 => RetCode::CredentialsExpired,
+            201 => RetCode::WorkerNotReady,
+            1400 => RetCode::BadRequest,
+            1401 => RetCode::Unauthorized,
+            1403 => RetCode::Forbidden,
+            1404 => RetCode::ApiNotFound,
+            code => RetCode::Other(code),
+        }
+    }
+}
+
+/// 调用 API 时的错误
+#[derive(Debug)]
+pub enum ApiError {
+    /// 请求未发出, 如连接不可用或无法建立连接, 可以安全地重试
+    NotSent(anyhow::Error),
+    /// 发送失败或连接在响应前断开, 请求可能已被执行
+    Transport(anyhow::Error),
+    /// 鉴权失败, 即 http 401 / 403
+    Auth(String),
+    /// 等待响应超时
+    Timeout(Duration),
+    /// 响应无法解析, `raw` 为原始 JSON
+    Decode {
+        error: serde_json::Error,
+        raw: String,
+    },
+    /// OneBot 实现返回 `status: "failed"`
+    Failed {
+        retcode: RetCode,
+        message: String,
+        wording: String,
+    },
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::NotSent(e) => write!(f, "request not sent: {}", e),
+            ApiError::Transport(e) => write!(f, "transport error: {}", e),
+            ApiError::Auth(e) => write!(f, "auth error: {}", e),
+            ApiError::Timeout(duration) => {
+                write!(f, "timeout receiving API response after {:?}", duration)
+            }
+            ApiError::Decode { error, raw } => {
+                write!(f, "error decoding API response: {}, raw: {}", error, raw)
+            }
+            ApiError::Failed {
+                retcode,
+                message,
+                wording,
+            } => write!(
+                f,
+                "API call failed, retcode: {}, message: {}, wording: {}",
+                retcode.code(),
+                message,
+                wording
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ApiError::NotSent(e) | ApiError::Transport(e) => Some(e.as_ref()),
+            ApiError::Decode { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for ApiError {
+    fn from(err: reqwest::Error) -> Self {
+        if err.is_connect() {
+            ApiError::NotSent(err.into())
+        } else {
+            ApiError::Transport(err.into())
+        }
+    }
+}
+
+impl From<tokio_tungstenite::tungstenite::Error> for ApiError {
+    fn from(err: tokio_tungstenite::tungstenite::Error) -> Self {
+        use tokio_tungstenite::tungstenite::Error;
+        match err {
+            // 连接已关闭, 消息没有被写入
+            Error::AlreadyClosed | Error::ConnectionClosed => ApiError::NotSent(err.into()),
+            err => ApiError::Transport(err.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::resp::ApiRespBuilder;
+
+    #[test]
+    fn retcode_round_trips_named_codes() {
+        for code in [100, 102, 103, 104, 201, 1400, 1401, 1403, 1404] {
+            let retcode = RetCode::from(code);
+            assert!(!matches!(retcode, RetCode::Other(_)), "{}", code);
+            assert_eq!(retcode.code(), code);
+        }
+        assert_eq!(RetCode::from(1404), RetCode::ApiNotFound);
+        assert_eq!(RetCode::from(1401), RetCode::Unauthorized);
+    }
+
+    #[test]
+    fn retcode_keeps_unknown_codes() {
+        assert_eq!(RetCode::from(0), RetCode::Other(0));
+        assert_eq!(RetCode::from(-1), RetCode::Other(-1));
+        assert_eq!(RetCode::from(1402).code(), 1402);
+    }
+
+    #[test]
+    fn closed_websocket_is_not_sent() {
+        use tokio_tungstenite::tungstenite::Error;
+        assert!(matches!(
+            ApiError::from(Error::AlreadyClosed),
+            ApiError::NotSent(_)
+        ));
+        assert!(matches!(
+            ApiError::from(Error::Io(std::io::ErrorKind::BrokenPipe.into())),
+            ApiError::Transport(_)
+        ));
+    }
+
+    #[test]
+    fn failed_response_maps_to_api_error() {
+        let resp: ApiRespBuilder = serde_json::from_value(serde_json::json!({
+            "status": "failed",
+            "retcode": 100,
+            "data": null,
+            "message": "missing user_id",
+            "wording": "参数缺失",
+            "echo": "1",
+        }))
+        .unwrap();
+        match resp.check() {
+            Err(ApiError::Failed {
+                retcode,
+                message,
+                wording,
+            }) => {
+                assert_eq!(retcode, RetCode::InvalidParams);
+                assert_eq!(message, "missing user_id");
+                assert_eq!(wording, "参数缺失");
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
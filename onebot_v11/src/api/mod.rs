@@ -1,3 +1,4 @@
+pub mod error;
 pub mod payload;
 pub mod resp;
 pub mod util;
@@ -1,7 +1,12 @@
-use crate::{event::message::Anonymous, traits::EndPoint, MessageSegment};
+use crate::{
+    event::{message::Anonymous, QuickOperation},
+    traits::EndPoint,
+    Event, MessageSegment,
+};
 use onebot_v11_macro::{endpoint, ApiDataDerive};
 use serde::{Deserialize, Serialize};
 
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, PartialEq, Eq, ApiDataDerive)]
 pub enum ApiPayload {
     /// 发送私聊消息
@@ -80,6 +85,8 @@ pub enum ApiPayload {
     SetRestart(SetRestart),
     /// 清理 OneBot 实现缓存
     CleanCache(CleanCache),
+    /// 对事件执行快速操作（隐藏 API）
+    HandleQuickOperation(HandleQuickOperation),
 
     // NapCat / llOneBot扩展
     /// 设置头像
@@ -486,6 +493,16 @@ pub struct SetRestart {
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct CleanCache {}
 
+/// 对事件执行快速操作结构体
+#[endpoint(".handle_quick_operation")]
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct HandleQuickOperation {
+    /// 事件数据对象，原样传入收到的事件
+    pub context: Event,
+    /// 快速操作对象
+    pub operation: QuickOperation,
+}
+
 // NapCat / llOneBot扩展
 
 /// 设置头像✔
@@ -1,12 +1,18 @@
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+use super::error::ApiError;
 use crate::message::segment::MessageSegment;
 
+/// `status` 字段表示请求的状态：
+/// - `ok` 表示操作成功, 同时 `retcode` （返回码）会等于 0
+/// - `async` 表示请求已提交异步处理, 此时 `retcode` 为 1, 具体成功或失败将无法获知
+/// - `failed` 表示操作失败, 此时 `retcode` 既不是 0 也不是 1, 调用会返回 `ApiError::Failed`
 #[derive(Serialize, Debug, Clone, PartialEq, Eq)]
 pub struct ApiResp {
     pub status: String,
-    pub retcode: u32,
+    pub retcode: i64,
     pub data: ApiRespData,
     #[serde(default)]
     pub echo: String,
@@ -15,25 +21,92 @@ pub struct ApiResp {
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct ApiRespBuilder {
     pub status: String,
-    pub retcode: u32,
+    pub retcode: i64,
     /// ApiRespData，但是无法直接序列化，提供一个`type_id`
+    #[serde(default)]
     pub data: Value,
+    /// 失败时的错误信息
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// 失败时对错误的详细解释
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wording: Option<String>,
     #[serde(default)]
     pub echo: String,
 }
 
 impl ApiRespBuilder {
-    pub fn build(self, resp_type: u8) -> Result<ApiResp, anyhow::Error> {
-        let data = ApiRespData::from_resp_type(resp_type, self.data)?;
+    /// `status` 为 `failed` 时返回 `ApiError::Failed`
+    pub fn check(self) -> Result<Self, ApiError> {
+        if self.status == "failed" {
+            return Err(ApiError::Failed {
+                retcode: self.retcode.into(),
+                message: self.message.unwrap_or_default(),
+                wording: self.wording.unwrap_or_default(),
+            });
+        }
+        Ok(self)
+    }
+
+    pub fn build(self, resp_type: u8) -> Result<ApiResp, ApiError> {
+        let resp = self.check()?;
+        let data = match resp.status.as_str() {
+            "ok" => ApiRespData::from_resp_type(resp_type, resp.data.clone()).map_err(|error| {
+                ApiError::Decode {
+                    error,
+                    raw: resp.data.to_string(),
+                }
+            })?,
+            "async" => ApiRespData::Accepted,
+            _ => ApiRespData::NoResponse(None),
+        };
         Ok(ApiResp {
-            status: self.status,
-            retcode: self.retcode,
+            status: resp.status,
+            retcode: resp.retcode,
             data,
-            echo: self.echo,
+            echo: resp.echo,
+        })
+    }
+
+    /// 以 `_async` 或 `_rate_limited` 调用后, 确认请求已被接受
+    pub fn accepted(self) -> Result<Accepted, ApiError> {
+        let resp = self.check()?;
+        Ok(Accepted {
+            retcode: resp.retcode,
+        })
+    }
+
+    /// 将 `data` 解析为指定类型
+    pub fn decode<T: DeserializeOwned>(self) -> Result<T, ApiError> {
+        let resp = self.check()?;
+        serde_json::from_value(resp.data.clone()).map_err(|error| ApiError::Decode {
+            error,
+            raw: resp.data.to_string(),
         })
     }
 }
 
+/// 异步调用的结果, 仅表示请求已被 OneBot 实现接受
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Accepted {
+    pub retcode: i64,
+}
+
+/// 没有响应数据的 API 的响应类型, 忽略 `data` 字段的内容
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct EmptyResponse;
+
+impl<'de> Deserialize<'de> for EmptyResponse {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        serde::de::IgnoredAny::deserialize(deserializer)?;
+        Ok(EmptyResponse)
+    }
+}
+
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ApiRespData {
     SendPrivateMsgResponse(SendPrivateMsgResponse),
@@ -60,6 +133,8 @@ pub enum ApiRespData {
     GetStatusResponse(GetStatusResponse),
     GetVersionInfoResponse(GetVersionInfoResponse),
     NoResponse(Option<()>),
+    /// 以 `_async` 或 `_rate_limited` 调用时, OneBot 实现已接受请求, 不包含操作结果
+    Accepted,
 
     // NapCat / llOneBot扩展
     /// 获取群系统通知
@@ -118,6 +193,7 @@ impl Serialize for ApiRespData {
             ApiRespData::GetStatusResponse(data) => data.serialize(serializer),
             ApiRespData::GetVersionInfoResponse(data) => data.serialize(serializer),
             ApiRespData::NoResponse(data) => data.serialize(serializer),
+            ApiRespData::Accepted => serializer.serialize_none(),
             // NapCat / llOneBot扩展
             ApiRespData::GetGroupSystemMsgResponse(data) => data.serialize(serializer),
             ApiRespData::GetFileResponse(data) => data.serialize(serializer),
@@ -1,6 +1,83 @@
 use serde_json::Value;
 
-use super::{payload::ApiPayload, resp::ApiRespData};
+use super::{payload::*, resp::*};
+use crate::traits::ApiRequest;
+
+macro_rules! impl_api_request {
+    ($($payload:ident => $resp:ty),* $(,)?) => {
+        $(
+            impl From<$payload> for ApiPayload {
+                fn from(payload: $payload) -> Self {
+                    ApiPayload::$payload(payload)
+                }
+            }
+
+            impl ApiRequest for $payload {
+                type Response = $resp;
+            }
+        )*
+    };
+}
+
+impl_api_request! {
+    SendPrivateMsg => SendPrivateMsgResponse,
+    SendGroupMsg => SendGroupMsgResponse,
+    SendMsg => SendMsgResponse,
+    DeleteMsg => DeleteMsgResponse,
+    GetMsg => GetMsgResponse,
+    GetForwardMsg => GetForwardMsgResponse,
+    SendLike => EmptyResponse,
+    SetGroupKick => EmptyResponse,
+    SetGroupBan => EmptyResponse,
+    SetGroupAnonymousBan => EmptyResponse,
+    SetGroupWholeBan => EmptyResponse,
+    SetGroupAdmin => EmptyResponse,
+    SetGroupAnonymous => EmptyResponse,
+    SetGroupCard => EmptyResponse,
+    SetGroupName => EmptyResponse,
+    SetGroupLeave => EmptyResponse,
+    SetGroupSpecialTitle => EmptyResponse,
+    SetFriendAddRequest => EmptyResponse,
+    SetGroupAddRequest => EmptyResponse,
+    GetLoginInfo => GetLoginInfoResponse,
+    GetStrangerInfo => GetStrangerInfoResponse,
+    GetFriendList => Vec<GetFriendListResponseItem>,
+    GetGroupInfo => GetGroupInfoResponse,
+    GetGroupList => Vec<GetGroupListResponseItem>,
+    GetGroupMemberInfo => GetGroupMemberInfoResponse,
+    GetGroupMemberList => Vec<GetGroupMemberListResponseItem>,
+    GetGroupHonorInfo => GetGroupHonorInfoResponse,
+    GetCookies => GetCookiesResponse,
+    GetCsrfToken => GetCsrfTokenResponse,
+    GetCredentials => GetCredentialsResponse,
+    GetRecord => GetRecordResponse,
+    GetImage => GetImageResponse,
+    CanSendImage => CanSendImageResponse,
+    CanSendRecord => CanSendRecordResponse,
+    GetStatus => GetStatusResponse,
+    GetVersionInfo => GetVersionInfoResponse,
+    SetRestart => EmptyResponse,
+    CleanCache => EmptyResponse,
+    HandleQuickOperation => EmptyResponse,
+    SetQQAvatar => EmptyResponse,
+    GetGroupSystemMsg => GetGroupSystemMsgResponse,
+    GetFile => GetFileResponse,
+    ForwardFriendSingleMsg => EmptyResponse,
+    ForwardGroupSingleMsg => EmptyResponse,
+    SetMsgEmojiLike => EmptyResponse,
+    MarkPrivateMsgAsRead => EmptyResponse,
+    MarkGroupMsgAsRead => EmptyResponse,
+    GetRobotUinRange => Vec<GetRobotUinRangeResponseItem>,
+    SetOnlineStatus => EmptyResponse,
+    GetFriendsWithCategory => Vec<GetFriendsWithCategoryResponseItem>,
+    GetGroupFileCount => GetGroupFileCountResponse,
+    GetGroupFileList => GetGroupFileListResponse,
+    SetGroupFileFolder => SetGroupFileFolderResponse,
+    DelGroupFile => DelGroupFileResponse,
+    DelGroupFileFolder => CommonClientResponseResult,
+    SendGroupForwardMsg => SendGroupForwardMsgResponse,
+    SendPrivateForwardMsg => SendPrivateForwardMsgResponse,
+}
 
 impl ApiPayload {
     pub fn to_resp_type(&self) -> u8 {
@@ -61,12 +138,13 @@ impl ApiPayload {
             ApiPayload::DelGroupFileFolder(_) => 54,
             ApiPayload::SendGroupForwardMsg(_) => 55,
             ApiPayload::SendPrivateForwardMsg(_) => 56,
+            ApiPayload::HandleQuickOperation(_) => 57,
         }
     }
 }
 
 impl ApiRespData {
-    pub fn from_resp_type(resp_type: u8, data: Value) -> Result<ApiRespData, anyhow::Error> {
+    pub fn from_resp_type(resp_type: u8, data: Value) -> Result<ApiRespData, serde_json::Error> {
         match resp_type {
             1 => Ok(ApiRespData::SendPrivateMsgResponse(serde_json::from_value(
                 data,
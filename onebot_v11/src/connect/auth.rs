@@ -0,0 +1,229 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use subtle::ConstantTimeEq as _;
+
+use super::WsType;
+
+/// 反向 ws 连接请求中用于鉴权的信息
+pub struct AuthRequest<'a> {
+    /// 远程地址
+    pub addr: SocketAddr,
+    /// 请求路径
+    pub path: &'a str,
+    /// `X-Self-ID` 头中的机器人 QQ 号
+    pub bot_id: &'a str,
+    pub r#type: &'a WsType,
+    /// 按 `token_placement` 读取的 access token, 请勿输出到日志
+    pub token: Option<&'a str>,
+}
+
+/// 拒绝连接的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthRejection {
+    /// 缺少或错误的凭证, 返回 401
+    Unauthorized(String),
+    /// 凭证正确但不允许连接, 返回 403
+    Forbidden(String),
+}
+
+impl fmt::Display for AuthRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthRejection::Unauthorized(reason) => write!(f, "unauthorized: {}", reason),
+            AuthRejection::Forbidden(reason) => write!(f, "forbidden: {}", reason),
+        }
+    }
+}
+
+/// 反向 ws 的鉴权与准入控制, 在 WebSocket 握手时调用
+pub trait Authenticator: Send + Sync {
+    fn authenticate(&self, request: &AuthRequest<'_>) -> Result<(), AuthRejection>;
+}
+
+/// 内置的鉴权实现, 所有名单为空时不做限制
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct AccessControl {
+    /// 未在 `bot_tokens` 中单独设置的机器人使用的 access token, 为空时不校验
+    pub access_token: Option<String>,
+    /// 每个机器人单独的 access token
+    pub bot_tokens: HashMap<String, String>,
+    /// 允许连接的机器人 QQ 号
+    pub allowed_bots: HashSet<String>,
+    /// 允许连接的远程地址
+    pub allowed_addrs: HashSet<IpAddr>,
+}
+
+impl fmt::Debug for AccessControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AccessControl")
+            .field("access_token", &redact(self.access_token.as_deref()))
+            .field("bot_tokens", &self.bot_tokens.keys().collect::<Vec<_>>())
+            .field("allowed_bots", &self.allowed_bots)
+            .field("allowed_addrs", &self.allowed_addrs)
+            .finish()
+    }
+}
+
+impl Authenticator for AccessControl {
+    fn authenticate(&self, request: &AuthRequest<'_>) -> Result<(), AuthRejection> {
+        if !self.allowed_addrs.is_empty()
+            && !self
+                .allowed_addrs
+                .iter()
+                .any(|addr| addr.to_canonical() == request.addr.ip().to_canonical())
+        {
+            return Err(AuthRejection::Forbidden(format!(
+                "address {} not allowed",
+                request.addr.ip()
+            )));
+        }
+        if !self.allowed_bots.is_empty() && !self.allowed_bots.contains(request.bot_id) {
+            return Err(AuthRejection::Forbidden(format!(
+                "bot_id {} not allowed",
+                request.bot_id
+            )));
+        }
+        let expected = self
+            .bot_tokens
+            .get(request.bot_id)
+            .or(self.access_token.as_ref());
+        match (expected, request.token) {
+            (None, _) => Ok(()),
+            (Some(_), None) => Err(AuthRejection::Unauthorized(
+                "access token not provided".to_string(),
+            )),
+            (Some(expected), Some(token)) => {
+                if bool::from(expected.as_bytes().ct_eq(token.as_bytes())) {
+                    Ok(())
+                } else {
+                    Err(AuthRejection::Unauthorized(
+                        "access token mismatch".to_string(),
+                    ))
+                }
+            }
+        }
+    }
+}
+
+/// 日志中代替凭证输出的占位符
+pub(crate) fn redact(token: Option<&str>) -> &'static str {
+    match token {
+        Some(_) => "<redacted>",
+        None => "<none>",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request<'a>(addr: &str, bot_id: &'a str, token: Option<&'a str>) -> AuthRequest<'a> {
+        AuthRequest {
+            addr: addr.parse().unwrap(),
+            path: "/onebot/v11",
+            bot_id,
+            r#type: &WsType::Universal,
+            token,
+        }
+    }
+
+    fn unauthorized(result: Result<(), AuthRejection>) -> bool {
+        matches!(result, Err(AuthRejection::Unauthorized(_)))
+    }
+
+    fn forbidden(result: Result<(), AuthRejection>) -> bool {
+        matches!(result, Err(AuthRejection::Forbidden(_)))
+    }
+
+    #[test]
+    fn accepts_everything_without_restrictions() {
+        let control = AccessControl::default();
+        assert!(control
+            .authenticate(&request("127.0.0.1:1", "10001", None))
+            .is_ok());
+        assert!(control
+            .authenticate(&request("127.0.0.1:1", "10001", Some("any")))
+            .is_ok());
+    }
+
+    #[test]
+    fn per_bot_token_overrides_global_token() {
+        let control = AccessControl {
+            access_token: Some("global".to_string()),
+            bot_tokens: HashMap::from([("10001".to_string(), "bot".to_string())]),
+            ..Default::default()
+        };
+        let auth = |bot_id, token| control.authenticate(&request("127.0.0.1:1", bot_id, token));
+        assert!(auth("10001", Some("bot")).is_ok());
+        assert!(unauthorized(auth("10001", Some("global"))));
+        assert!(auth("10002", Some("global")).is_ok());
+        assert!(unauthorized(auth("10002", Some("bot"))));
+        assert!(unauthorized(auth("10002", None)));
+    }
+
+    #[test]
+    fn rejects_bots_outside_allowlist() {
+        let control = AccessControl {
+            allowed_bots: HashSet::from(["10001".to_string()]),
+            ..Default::default()
+        };
+        assert!(control
+            .authenticate(&request("127.0.0.1:1", "10001", None))
+            .is_ok());
+        assert!(forbidden(control.authenticate(&request(
+            "127.0.0.1:1",
+            "10002",
+            None
+        ))));
+    }
+
+    #[test]
+    fn address_allowlist_matches_ipv4_mapped_addresses() {
+        let control = AccessControl {
+            allowed_addrs: HashSet::from(["127.0.0.1".parse().unwrap()]),
+            ..Default::default()
+        };
+        let auth = |addr| control.authenticate(&request(addr, "10001", None));
+        assert!(auth("127.0.0.1:1").is_ok());
+        assert!(auth("[::ffff:127.0.0.1]:1").is_ok());
+        assert!(forbidden(auth("10.0.0.1:1")));
+        assert!(forbidden(auth("[::1]:1")));
+
+        let control = AccessControl {
+            allowed_addrs: HashSet::from(["::ffff:10.0.0.1".parse().unwrap()]),
+            ..Default::default()
+        };
+        assert!(control
+            .authenticate(&request("10.0.0.1:1", "10001", None))
+            .is_ok());
+    }
+
+    #[test]
+    fn forbidden_takes_precedence_over_token_check() {
+        let control = AccessControl {
+            access_token: Some("secret".to_string()),
+            allowed_bots: HashSet::from(["10001".to_string()]),
+            ..Default::default()
+        };
+        assert!(forbidden(control.authenticate(&request(
+            "127.0.0.1:1",
+            "10002",
+            Some("secret")
+        ))));
+    }
+
+    #[test]
+    fn debug_redacts_tokens() {
+        let control = AccessControl {
+            access_token: Some("global-secret".to_string()),
+            bot_tokens: HashMap::from([("10001".to_string(), "bot-secret".to_string())]),
+            ..Default::default()
+        };
+        let debug = format!("{:?}", control);
+        assert!(!debug.contains("secret"));
+        assert!(debug.contains("10001"));
+        assert_eq!(redact(Some("secret")), "<redacted>");
+        assert_eq!(redact(None), "<none>");
+    }
+}
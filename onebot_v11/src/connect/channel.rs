@@ -0,0 +1,244 @@
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::{broadcast, mpsc, Mutex};
+use tracing::warn;
+
+use crate::Event;
+
+/// 事件通道配置
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EventChannelConfig {
+    /// 通道容量, 即每个订阅者 (`Broadcast`) 或共享队列 (`Lossless`) 最多缓存的事件数
+    pub capacity: usize,
+    pub mode: EventChannelMode,
+}
+
+impl Default for EventChannelConfig {
+    fn default() -> Self {
+        EventChannelConfig {
+            capacity: 100,
+            mode: EventChannelMode::Broadcast,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum EventChannelMode {
+    /// 每个订阅者都会收到所有事件, 处理过慢的订阅者会丢失事件, 丢失数量计入 `dropped_events`
+    Broadcast,
+    /// 容量为 `capacity` 的有界队列, 所有订阅者共享同一队列, 每个事件只会被其中一个订阅者收到.
+    /// 队列已满时:
+    /// - HTTP 上报与 HTTP 轮询等待订阅者取走事件后再继续接收 (背压), 不会丢失事件;
+    /// - ws 连接的事件与 API 响应共用连接, 为了不阻塞 API 响应不会暂停读取,
+    ///   而是丢弃当前事件 (计入 `dropped_events`) 并断开连接, 之后正向 ws 按重连策略重连,
+    ///   反向 ws 等待机器人重新连接. 已在队列中的事件不受影响
+    Lossless,
+}
+
+/// 连接内部的事件通道
+pub(crate) struct EventChannel {
+    sender: EventSender,
+    dropped: Arc<AtomicU64>,
+}
+
+enum EventSender {
+    Broadcast(broadcast::Sender<Event>),
+    Lossless {
+        sender: mpsc::Sender<Event>,
+        receiver: Arc<Mutex<mpsc::Receiver<Event>>>,
+    },
+}
+
+impl EventChannel {
+    pub(crate) fn new(config: &EventChannelConfig) -> Self {
+        let capacity = config.capacity.max(1);
+        let sender = match config.mode {
+            EventChannelMode::Broadcast => EventSender::Broadcast(broadcast::channel(capacity).0),
+            EventChannelMode::Lossless => {
+                let (sender, receiver) = mpsc::channel(capacity);
+                EventSender::Lossless {
+                    sender,
+                    receiver: Arc::new(Mutex::new(receiver)),
+                }
+            }
+        };
+        EventChannel {
+            sender,
+            dropped: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// 发送事件, `Lossless` 模式下队列已满时等待订阅者取走事件
+    pub(crate) async fn send(&self, event: Event) {
+        match &self.sender {
+            EventSender::Broadcast(sender) => {
+                if let Err(e) = sender.send(event) {
+                    warn!("Error sending Event: {}", e);
+                }
+            }
+            EventSender::Lossless { sender, .. } => {
+                if let Err(e) = sender.send(event).await {
+                    warn!("Error sending Event: {}", e);
+                }
+            }
+        }
+    }
+
+    /// 发送事件, 不会阻塞调用方. `Lossless` 模式下队列已满时丢弃事件并返回 `false`
+    pub(crate) fn try_send(&self, event: Event) -> bool {
+        match &self.sender {
+            EventSender::Broadcast(sender) => {
+                if let Err(e) = sender.send(event) {
+                    warn!("Error sending Event: {}", e);
+                }
+                true
+            }
+            EventSender::Lossless { sender, .. } => match sender.try_send(event) {
+                Ok(()) => true,
+                Err(mpsc::error::TrySendError::Full(_)) => {
+                    warn!("Event queue is full, event dropped");
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    false
+                }
+                Err(e) => {
+                    warn!("Error sending Event: {}", e);
+                    true
+                }
+            },
+        }
+    }
+
+    pub(crate) fn subscribe(&self) -> EventReceiver {
+        let inner = match &self.sender {
+            EventSender::Broadcast(sender) => EventReceiverInner::Broadcast(sender.subscribe()),
+            EventSender::Lossless { receiver, .. } => {
+                EventReceiverInner::Lossless(receiver.clone())
+            }
+        };
+        EventReceiver {
+            inner,
+            dropped: self.dropped.clone(),
+        }
+    }
+
+    /// 所有订阅者因处理过慢而丢失的事件总数
+    pub(crate) fn dropped_events(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+/// 事件订阅者, 通过 `subscribe()` 获取
+pub struct EventReceiver {
+    inner: EventReceiverInner,
+    dropped: Arc<AtomicU64>,
+}
+
+enum EventReceiverInner {
+    Broadcast(broadcast::Receiver<Event>),
+    Lossless(Arc<Mutex<mpsc::Receiver<Event>>>),
+}
+
+impl EventReceiver {
+    /// 接收下一个事件, 连接已被释放时返回 `None`.
+    /// 广播模式下处理过慢时会跳过已丢失的事件并记录数量
+    pub async fn recv(&mut self) -> Option<Event> {
+        match &mut self.inner {
+            EventReceiverInner::Broadcast(receiver) => loop {
+                match receiver.recv().await {
+                    Ok(event) => return Some(event),
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        warn!("Event receiver lagged, {} events dropped", n);
+                        self.dropped.fetch_add(n, Ordering::Relaxed);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            },
+            EventReceiverInner::Lossless(receiver) => receiver.lock().await.recv().await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::meta::{Heartbeat, Meta};
+
+    fn event(time: i64) -> Event {
+        Event::Meta(Meta::Heartbeat(
+            serde_json::from_value(serde_json::json!({
+                "time": time,
+                "self_id": 10001,
+                "post_type": "meta_event",
+                "meta_event_type": "heartbeat",
+                "status": {"online": true, "good": true},
+                "interval": 5000,
+            }))
+            .unwrap(),
+        ))
+    }
+
+    fn time(event: Event) -> i64 {
+        match event {
+            Event::Meta(Meta::Heartbeat(Heartbeat { time, .. })) => time,
+            _ => panic!("unexpected event"),
+        }
+    }
+
+    #[tokio::test]
+    async fn lossless_send_waits_when_full() {
+        let channel = Arc::new(EventChannel::new(&EventChannelConfig {
+            capacity: 4,
+            mode: EventChannelMode::Lossless,
+        }));
+        let mut receiver = channel.subscribe();
+        let sender = tokio::spawn({
+            let channel = channel.clone();
+            async move {
+                for i in 0..100 {
+                    channel.send(event(i)).await;
+                }
+            }
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(!sender.is_finished());
+        for i in 0..100 {
+            assert_eq!(time(receiver.recv().await.unwrap()), i);
+        }
+        sender.await.unwrap();
+        assert_eq!(channel.dropped_events(), 0);
+    }
+
+    #[tokio::test]
+    async fn lossless_try_send_rejects_when_full() {
+        let channel = EventChannel::new(&EventChannelConfig {
+            capacity: 4,
+            mode: EventChannelMode::Lossless,
+        });
+        let mut receiver = channel.subscribe();
+        for i in 0..4 {
+            assert!(channel.try_send(event(i)));
+        }
+        assert!(!channel.try_send(event(4)));
+        assert_eq!(channel.dropped_events(), 1);
+        for i in 0..4 {
+            assert_eq!(time(receiver.recv().await.unwrap()), i);
+        }
+        assert!(channel.try_send(event(5)));
+        assert_eq!(time(receiver.recv().await.unwrap()), 5);
+    }
+
+    #[tokio::test]
+    async fn broadcast_counts_dropped_events() {
+        let channel = EventChannel::new(&EventChannelConfig {
+            capacity: 4,
+            mode: EventChannelMode::Broadcast,
+        });
+        let mut receiver = channel.subscribe();
+        for i in 0..10 {
+            assert!(channel.try_send(event(i)));
+        }
+        assert_eq!(time(receiver.recv().await.unwrap()), 6);
+        assert_eq!(channel.dropped_events(), 6);
+    }
+}
@@ -0,0 +1,495 @@
+use async_trait::async_trait;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::interval;
+use tracing::{debug, info, warn};
+
+use crate::api::error::ApiError;
+use crate::api::payload::{ApiPayload, GetStatus};
+use crate::api::resp::ApiRespBuilder;
+use crate::traits::{OneBotClient, OneBotClientExt as _};
+
+use super::channel::{EventChannel, EventChannelConfig, EventReceiver};
+use super::{shutdown_signal, CallOptions};
+
+/// 故障转移配置
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FailoverConfig {
+    /// 设置后按该间隔探测优先级更高的后端, 探测成功时切回
+    pub fail_back: Option<Duration>,
+    /// 设置后按该间隔检查当前后端的连接状态, 断开时切换到下一个已连接的后端,
+    /// 使只接收事件、不调用 API 的场景也能故障转移
+    pub health_check: Option<Duration>,
+    /// 探测使用的 `get_status` 超时时间
+    pub probe_timeout: Duration,
+    /// 事件通道容量与模式
+    pub events: EventChannelConfig,
+}
+
+impl Default for FailoverConfig {
+    fn default() -> Self {
+        FailoverConfig {
+            fail_back: None,
+            health_check: Some(Duration::from_secs(1)),
+            probe_timeout: Duration::from_secs(5),
+            events: EventChannelConfig::default(),
+        }
+    }
+}
+
+/// 按优先级排列的多个后端, 当前后端不可用时切换到下一个.
+/// 调用返回 `ApiError::NotSent` 时请求未发出, 在下一个后端上重试该调用;
+/// 返回 `ApiError::Transport` 或 `ApiError::Timeout` 时请求可能已被执行, 因此只切换后端而不重试.
+/// 只会切换到已连接且在本次调用中未失败的后端, 没有这样的后端时保持不变.
+/// 当前后端断开连接时由 `health_check` 切换后端.
+/// 事件只转发自当前使用的后端, 切换前后的订阅者无需重新订阅
+pub struct FailoverConnect {
+    pub config: FailoverConfig,
+    endpoints: Vec<Arc<dyn OneBotClient>>,
+    active: Arc<AtomicUsize>,
+    events: Arc<EventChannel>,
+    shutdown: watch::Sender<bool>,
+    tasks: std::sync::Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl FailoverConnect {
+    /// `endpoints` 按优先级从高到低排列, 初始使用第一个
+    pub async fn new(
+        endpoints: Vec<Arc<dyn OneBotClient>>,
+        config: FailoverConfig,
+    ) -> Result<Arc<Self>, anyhow::Error> {
+        if endpoints.is_empty() {
+            return Err(anyhow::anyhow!(
+                "FailoverConnect requires at least one endpoint"
+            ));
+        }
+        let self_ = Arc::new(Self {
+            events: Arc::new(EventChannel::new(&config.events)),
+            config,
+            endpoints,
+            active: Arc::new(AtomicUsize::new(0)),
+            shutdown: watch::channel(false).0,
+            tasks: std::sync::Mutex::new(Vec::new()),
+        });
+
+        let mut tasks = Vec::new();
+        for (index, endpoint) in self_.endpoints.iter().enumerate() {
+            if let Some(receiver) = endpoint.subscribe().await {
+                tasks.push(tokio::spawn(forward_events(
+                    index,
+                    receiver,
+                    self_.active.clone(),
+                    Arc::downgrade(&self_.events),
+                    self_.shutdown.subscribe(),
+                )));
+            }
+        }
+        if let Some(period) = self_.config.health_check {
+            tasks.push(tokio::spawn(health_check(
+                Arc::downgrade(&self_),
+                period,
+                self_.shutdown.subscribe(),
+            )));
+        }
+        if let Some(period) = self_.config.fail_back {
+            tasks.push(tokio::spawn(fail_back(
+                Arc::downgrade(&self_),
+                period,
+                self_.shutdown.subscribe(),
+            )));
+        }
+        *self_.tasks.lock().unwrap() = tasks;
+        Ok(self_)
+    }
+
+    /// 停止转发与探测并等待任务退出, 随后依次关闭所有后端
+    pub async fn shutdown(&self) {
+        self.shutdown.send_replace(true);
+        let tasks = std::mem::take(&mut *self.tasks.lock().unwrap());
+        for task in tasks {
+            if let Err(e) = task.await {
+                warn!("Error joining failover task: {}", e);
+            }
+        }
+        for endpoint in &self.endpoints {
+            endpoint.shutdown().await;
+        }
+    }
+
+    /// 当前使用的后端序号
+    pub fn active(&self) -> usize {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    pub fn endpoints(&self) -> &[Arc<dyn OneBotClient>] {
+        &self.endpoints
+    }
+
+    pub async fn subscribe(&self) -> EventReceiver {
+        self.events.subscribe()
+    }
+
+    /// 订阅者因处理过慢而丢失的事件总数
+    pub fn dropped_events(&self) -> u64 {
+        self.events.dropped_events()
+    }
+
+    /// 仅在当前后端仍为 `from` 时切换, 避免并发调用重复切换
+    fn switch(&self, from: usize, to: usize, reason: &dyn std::fmt::Display) {
+        if self
+            .active
+            .compare_exchange(from, to, Ordering::Relaxed, Ordering::Relaxed)
+            .is_ok()
+        {
+            warn!(
+                "Endpoint {} unavailable: {}, failing over to endpoint {}",
+                from, reason, to
+            );
+        }
+    }
+
+    /// `from` 之后第一个已连接且不在 `failed` 中的后端
+    fn next_connected(&self, from: usize, failed: &[usize]) -> Option<usize> {
+        let len = self.endpoints.len();
+        (1..len)
+            .map(|offset| (from + offset) % len)
+            .find(|index| !failed.contains(index) && self.endpoints[*index].is_connected())
+    }
+
+    /// 当前后端断开时切换到其后第一个已连接的后端, 没有已连接的后端时保持不变
+    fn check_active(&self) {
+        let active = self.active();
+        if self.endpoints[active].is_connected() {
+            return;
+        }
+        if let Some(next) = self.next_connected(active, &[]) {
+            self.switch(active, next, &"disconnected");
+        }
+    }
+}
+
+#[async_trait]
+impl OneBotClient for FailoverConnect {
+    async fn call_api_raw(
+        &self,
+        api_data: ApiPayload,
+        options: CallOptions,
+    ) -> Result<ApiRespBuilder, ApiError> {
+        let mut index = self.active();
+        let mut failed = Vec::new();
+        loop {
+            let result = self.endpoints[index]
+                .call_api_raw(api_data.clone(), options.clone())
+                .await;
+            match result {
+                Err(e @ (ApiError::NotSent(_) | ApiError::Transport(_) | ApiError::Timeout(_))) => {
+                    failed.push(index);
+                    let Some(next) = self.next_connected(index, &failed) else {
+                        return Err(e);
+                    };
+                    self.switch(index, next, &e);
+                    if !matches!(e, ApiError::NotSent(_)) {
+                        return Err(e);
+                    }
+                    index = next;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn subscribe(&self) -> Option<EventReceiver> {
+        Some(self.events.subscribe())
+    }
+
+    /// 任意一个后端已连接时返回 `true`
+    fn is_connected(&self) -> bool {
+        self.endpoints
+            .iter()
+            .any(|endpoint| endpoint.is_connected())
+    }
+
+    async fn shutdown(&self) {
+        FailoverConnect::shutdown(self).await
+    }
+}
+
+async fn forward_events(
+    index: usize,
+    mut receiver: EventReceiver,
+    active: Arc<AtomicUsize>,
+    events: Weak<EventChannel>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    loop {
+        let event = tokio::select! {
+            event = receiver.recv() => event,
+            _ = shutdown_signal(&mut shutdown) => break,
+        };
+        let Some(event) = event else {
+            break;
+        };
+        let Some(events) = events.upgrade() else {
+            break;
+        };
+        if active.load(Ordering::Relaxed) == index {
+            tokio::select! {
+                _ = events.send(event) => {}
+                _ = shutdown_signal(&mut shutdown) => break,
+            }
+        }
+    }
+}
+
+/// 按 `period` 定期检查当前后端, 直到 `FailoverConnect` 被关闭或释放
+async fn health_check(
+    connect: Weak<FailoverConnect>,
+    period: Duration,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    let mut ticker = interval(period);
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {}
+            _ = shutdown_signal(&mut shutdown) => break,
+        }
+        let Some(connect) = connect.upgrade() else {
+            break;
+        };
+        connect.check_active();
+    }
+}
+
+/// 按 `period` 定期探测, 直到 `FailoverConnect` 被关闭或释放
+async fn fail_back(
+    connect: Weak<FailoverConnect>,
+    period: Duration,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    let mut ticker = interval(period);
+    ticker.tick().await;
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {}
+            _ = shutdown_signal(&mut shutdown) => break,
+        }
+        let Some(connect) = connect.upgrade() else {
+            break;
+        };
+        tokio::select! {
+            _ = probe(&connect) => {}
+            _ = shutdown_signal(&mut shutdown) => break,
+        }
+    }
+}
+
+/// 按优先级探测当前后端之前已连接的后端, 第一个状态正常的后端成为当前后端
+async fn probe(connect: &FailoverConnect) {
+    let active = connect.active();
+    let options = CallOptions {
+        timeout: Some(connect.config.probe_timeout),
+        ..Default::default()
+    };
+    for index in 0..active {
+        if !connect.endpoints[index].is_connected() {
+            continue;
+        }
+        match connect.endpoints[index]
+            .call_with(GetStatus {}, options.clone())
+            .await
+        {
+            Ok(status) if status.good => {
+                if connect
+                    .active
+                    .compare_exchange(active, index, Ordering::Relaxed, Ordering::Relaxed)
+                    .is_ok()
+                {
+                    info!("Endpoint {} recovered, failing back", index);
+                }
+                break;
+            }
+            Ok(_) => {}
+            Err(e) => debug!("Probing endpoint {} failed: {}", index, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::payload::GetStatus;
+    use std::sync::atomic::AtomicBool;
+
+    /// 可控制连接状态与调用结果的后端, 记录收到的调用次数
+    struct MockEndpoint {
+        connected: AtomicBool,
+        error: fn() -> ApiError,
+        calls: AtomicUsize,
+    }
+
+    impl MockEndpoint {
+        fn new(error: fn() -> ApiError) -> Arc<Self> {
+            Arc::new(MockEndpoint {
+                connected: AtomicBool::new(true),
+                error,
+                calls: AtomicUsize::new(0),
+            })
+        }
+
+        fn calls(&self) -> usize {
+            self.calls.load(Ordering::Relaxed)
+        }
+    }
+
+    #[async_trait]
+    impl OneBotClient for MockEndpoint {
+        async fn call_api_raw(
+            &self,
+            _api_data: ApiPayload,
+            _options: CallOptions,
+        ) -> Result<ApiRespBuilder, ApiError> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            Err((self.error)())
+        }
+
+        fn is_connected(&self) -> bool {
+            self.connected.load(Ordering::Relaxed)
+        }
+    }
+
+    fn not_sent() -> ApiError {
+        ApiError::NotSent(anyhow::anyhow!("not connected"))
+    }
+
+    fn transport() -> ApiError {
+        ApiError::Transport(anyhow::anyhow!("connection closed before API response"))
+    }
+
+    fn timeout() -> ApiError {
+        ApiError::Timeout(Duration::from_secs(1))
+    }
+
+    fn config() -> FailoverConfig {
+        FailoverConfig {
+            health_check: None,
+            ..Default::default()
+        }
+    }
+
+    async fn call(connect: &FailoverConnect) -> ApiError {
+        connect
+            .call_api_raw(ApiPayload::GetStatus(GetStatus {}), CallOptions::default())
+            .await
+            .unwrap_err()
+    }
+
+    #[tokio::test]
+    async fn retries_requests_that_were_not_sent() {
+        let first = MockEndpoint::new(not_sent);
+        let second = MockEndpoint::new(transport);
+        let connect = FailoverConnect::new(vec![first.clone(), second.clone()], config())
+            .await
+            .unwrap();
+
+        assert!(matches!(call(&connect).await, ApiError::Transport(_)));
+        assert_eq!((first.calls(), second.calls()), (1, 1));
+        // 第一个后端已在本次调用中失败, 不会切换回去
+        assert_eq!(connect.active(), 1);
+        connect.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn skips_disconnected_endpoints() {
+        let endpoints = [
+            MockEndpoint::new(timeout),
+            MockEndpoint::new(not_sent),
+            MockEndpoint::new(transport),
+        ];
+        let connect =
+            FailoverConnect::new(endpoints.iter().map(|e| e.clone() as _).collect(), config())
+                .await
+                .unwrap();
+
+        // 超时后跳过已断开的后端
+        endpoints[1].connected.store(false, Ordering::Relaxed);
+        assert!(matches!(call(&connect).await, ApiError::Timeout(_)));
+        assert_eq!(connect.active(), 2);
+
+        // 其余后端均已断开时保持不变
+        endpoints[0].connected.store(false, Ordering::Relaxed);
+        assert!(matches!(call(&connect).await, ApiError::Transport(_)));
+        assert_eq!(connect.active(), 2);
+        assert_eq!(endpoints.each_ref().map(|e| e.calls()), [1, 0, 1]);
+        connect.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn stays_put_when_no_other_endpoint_is_connected() {
+        let first = MockEndpoint::new(timeout);
+        let second = MockEndpoint::new(not_sent);
+        second.connected.store(false, Ordering::Relaxed);
+        let connect = FailoverConnect::new(vec![first.clone(), second.clone()], config())
+            .await
+            .unwrap();
+
+        assert!(matches!(call(&connect).await, ApiError::Timeout(_)));
+        assert_eq!((first.calls(), second.calls()), (1, 0));
+        assert_eq!(connect.active(), 0);
+        connect.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_requests_that_may_have_been_sent() {
+        for error in [transport as fn() -> ApiError, timeout] {
+            let first = MockEndpoint::new(error);
+            let second = MockEndpoint::new(not_sent);
+            let connect = FailoverConnect::new(vec![first.clone(), second.clone()], config())
+                .await
+                .unwrap();
+
+            call(&connect).await;
+            assert_eq!((first.calls(), second.calls()), (1, 0));
+            assert_eq!(connect.active(), 1);
+            connect.shutdown().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn health_check_fails_over_disconnected_endpoint() {
+        let endpoints = [
+            MockEndpoint::new(not_sent),
+            MockEndpoint::new(not_sent),
+            MockEndpoint::new(not_sent),
+        ];
+        let connect = FailoverConnect::new(
+            endpoints.iter().map(|e| e.clone() as _).collect(),
+            FailoverConfig {
+                health_check: Some(Duration::from_millis(10)),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        endpoints[0].connected.store(false, Ordering::Relaxed);
+        endpoints[1].connected.store(false, Ordering::Relaxed);
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while connect.active() != 2 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert!(connect.is_connected());
+
+        // 没有已连接的后端时保持不变
+        endpoints[2].connected.store(false, Ordering::Relaxed);
+        connect.check_active();
+        assert_eq!(connect.active(), 2);
+        assert!(!connect.is_connected());
+        connect.shutdown().await;
+    }
+}
@@ -1,16 +1,45 @@
 use crate::{
-    api::{payload::ApiPayload, resp::ApiRespData},
-    traits::EndPoint,
+    api::{
+        error::{ApiError, RetCode},
+        payload::ApiPayload,
+        resp::{ApiResp, ApiRespBuilder},
+    },
+    traits::{EndPoint, OneBotClient},
+    Event,
 };
+
+use super::auth::redact;
+use super::channel::{EventChannel, EventChannelConfig, EventReceiver};
+use super::proxy::{authority, ProxyConfig};
+use super::tls::TlsConfig;
+use super::{shutdown_signal, CallOptions, TokenPlacement};
+use async_trait::async_trait;
 use reqwest::StatusCode;
 use serde_json::Value;
-use tracing::warn;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::sleep;
+use tracing::{info, warn};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct HttpConfig {
     pub host: String,
     pub port: u16,
     pub access_token: Option<String>,
+    /// access token 的传递方式
+    pub token_placement: TokenPlacement,
+    /// 设置后使用 `https://` 连接
+    pub tls: Option<TlsConfig>,
+    /// 设置后通过代理发送请求
+    pub proxy: Option<ProxyConfig>,
+    /// 请求的默认超时时间, 可通过 `CallOptions` 单独设置
+    pub api_timeout: Duration,
+    /// 设置后通过长轮询获取事件, 需在 tokio 运行时中调用 `HttpConnect::new`
+    pub poll: Option<HttpPollConfig>,
+    /// 事件通道容量与模式, 仅在设置 `poll` 时使用
+    pub events: EventChannelConfig,
 }
 
 impl Default for HttpConfig {
@@ -19,53 +48,36 @@ impl Default for HttpConfig {
             host: "127.0.0.1".to_string(),
             port: 8080,
             access_token: None,
+            token_placement: TokenPlacement::default(),
+            tls: None,
+            proxy: None,
+            api_timeout: Duration::from_secs(30),
+            poll: None,
+            events: EventChannelConfig::default(),
         }
     }
 }
 
-/// `status` 字段表示请求的状态：
-/// - `ok` 表示操作成功, 同时 `retcode` （返回码）会等于 0
-/// - `async` 表示请求已提交异步处理, 此时 `retcode` 为 1, 具体成功或失败将无法获知
-/// - `failed` 表示操作失败, 此时 `retcode` 既不是 0 也不是 1, 具体错误信息应参考 OneBot 实现的日志
-#[derive(Debug)]
-pub struct HttpCallApiResp {
-    pub status: String,
-    pub retcode: String,
-    pub data: ApiRespData,
-}
-
-#[derive(Debug)]
-pub enum HttpCallApiError {
-    Unauthorized,
-    InvalidToken,
-    ContentTypeNotSupported,
-    InvalidRequestBody,
-    ApiNotFound,
-    UnKnown,
-    AnyhowError(anyhow::Error),
-    ReqwestError(reqwest::Error),
-}
-
-impl From<reqwest::Error> for HttpCallApiError {
-    fn from(err: reqwest::Error) -> Self {
-        HttpCallApiError::ReqwestError(err)
-    }
-}
-impl From<anyhow::Error> for HttpCallApiError {
-    fn from(err: anyhow::Error) -> Self {
-        HttpCallApiError::AnyhowError(err)
-    }
+/// HTTP 长轮询配置, 对应 OneBot 实现中的 `get_latest_events` 一类接口
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HttpPollConfig {
+    /// 轮询使用的 API 名称
+    pub action: String,
+    /// 每次最多获取的事件数, 0 表示不限制
+    pub limit: u32,
+    /// 没有新事件时 OneBot 实现等待的时间, 以秒为单位传递, 为 0 时立即返回
+    pub timeout: Duration,
+    /// 轮询失败后的重试间隔
+    pub retry_delay: Duration,
 }
 
-impl From<StatusCode> for HttpCallApiError {
-    fn from(value: StatusCode) -> Self {
-        match value {
-            StatusCode::UNAUTHORIZED => HttpCallApiError::Unauthorized,
-            StatusCode::FORBIDDEN => HttpCallApiError::InvalidToken,
-            StatusCode::NOT_ACCEPTABLE => HttpCallApiError::ContentTypeNotSupported,
-            StatusCode::BAD_REQUEST => HttpCallApiError::InvalidRequestBody,
-            StatusCode::NOT_FOUND => HttpCallApiError::ApiNotFound,
-            _ => HttpCallApiError::UnKnown,
+impl Default for HttpPollConfig {
+    fn default() -> Self {
+        HttpPollConfig {
+            action: "get_latest_events".to_string(),
+            limit: 0,
+            timeout: Duration::from_secs(30),
+            retry_delay: Duration::from_secs(3),
         }
     }
 }
@@ -73,77 +85,431 @@ impl From<StatusCode> for HttpCallApiError {
 pub struct HttpConnect {
     pub config: HttpConfig,
     pub client: reqwest::Client,
+    events: Arc<EventChannel>,
+    shutdown: watch::Sender<bool>,
+    poller: std::sync::Mutex<Option<JoinHandle<()>>>,
 }
 
 impl HttpConnect {
-    pub fn new(config: HttpConfig) -> Self {
-        HttpConnect {
+    /// 设置了 `poll` 时需在 Tokio 运行时中调用, 否则返回错误
+    pub fn new(config: HttpConfig) -> Result<Self, anyhow::Error> {
+        let runtime = match &config.poll {
+            Some(_) => Some(tokio::runtime::Handle::try_current().map_err(|e| {
+                anyhow::anyhow!("HTTP long polling requires a Tokio runtime: {}", e)
+            })?),
+            None => None,
+        };
+        let mut builder = reqwest::Client::builder();
+        if let Some(tls) = &config.tls {
+            builder = builder.use_preconfigured_tls(tls.client_config()?);
+        }
+        if let Some(proxy) = &config.proxy {
+            builder = builder.proxy(proxy.reqwest_proxy()?);
+        }
+        let self_ = HttpConnect {
+            events: Arc::new(EventChannel::new(&config.events)),
             config,
-            client: reqwest::Client::new(),
+            client: builder.build()?,
+            shutdown: watch::channel(false).0,
+            poller: std::sync::Mutex::new(None),
+        };
+        if let (Some(runtime), Some(poll)) = (runtime, self_.config.poll.clone()) {
+            let poller = runtime.spawn(poll_events(
+                self_.client.clone(),
+                self_.config.clone(),
+                poll,
+                Arc::downgrade(&self_.events),
+                self_.shutdown.subscribe(),
+            ));
+            *self_.poller.lock().unwrap() = Some(poller);
+        }
+        Ok(self_)
+    }
+
+    /// 停止长轮询并等待轮询任务退出, 之后的调用均会失败
+    pub async fn shutdown(&self) {
+        self.shutdown.send_replace(true);
+        let poller = self.poller.lock().unwrap().take();
+        if let Some(poller) = poller {
+            if let Err(e) = poller.await {
+                warn!("Error joining HTTP long polling task: {}", e);
+            }
         }
     }
 
-    pub async fn call_api(
+    /// 订阅长轮询获取的事件, 未设置 `poll` 时不会收到任何事件
+    pub async fn subscribe(&self) -> EventReceiver {
+        self.events.subscribe()
+    }
+
+    /// 订阅者因处理过慢而丢失的事件总数
+    pub fn dropped_events(&self) -> u64 {
+        self.events.dropped_events()
+    }
+
+    pub async fn call_api(&self, api_data: ApiPayload) -> Result<ApiResp, ApiError> {
+        self.call_api_with(api_data, CallOptions::default()).await
+    }
+
+    /// 使用指定选项调用 API
+    pub async fn call_api_with(
         &self,
         api_data: ApiPayload,
-    ) -> Result<HttpCallApiResp, HttpCallApiError> {
+        options: CallOptions,
+    ) -> Result<ApiResp, ApiError> {
         let resp_type = api_data.to_resp_type();
-        let url = format!(
-            "http://{}:{}/{}",
-            self.config.host,
-            self.config.port,
-            api_data.endpoint()
-        );
+        self.call_api_raw(api_data, options).await?.build(resp_type)
+    }
+
+    /// 调用 API 并返回未解析 `data` 的响应
+    pub async fn call_api_raw(
+        &self,
+        api_data: ApiPayload,
+        options: CallOptions,
+    ) -> Result<ApiRespBuilder, ApiError> {
+        if *self.shutdown.borrow() {
+            return Err(ApiError::NotSent(anyhow::anyhow!(
+                "[HttpConnect.call_api] Connection has been shut down"
+            )));
+        }
+        let url = self.config.url(&options.mode.action(&api_data.endpoint()));
 
-        let response_builder = self.client.post(url).json(&api_data);
-        let response = match &self.config.access_token {
-            Some(token) => response_builder.bearer_auth(token).send().await?,
-            _ => response_builder.send().await?,
+        let timeout = options.timeout.unwrap_or(self.config.api_timeout);
+        let map_err = |e: reqwest::Error| {
+            if e.is_timeout() {
+                ApiError::Timeout(timeout)
+            } else {
+                redact_url(e).into()
+            }
         };
+        let response = self
+            .config
+            .authorize(self.client.post(url))
+            .json(&api_data)
+            .timeout(timeout)
+            .send()
+            .await
+            .map_err(map_err)?;
         let status = response.status();
+        let text = response.text().await.map_err(map_err)?;
+        if status != StatusCode::OK {
+            warn!(
+                "http call api failed, raw resp: {}, raw req: {}",
+                text,
+                serde_json::to_string(&api_data).unwrap_or("Serialize Failed".to_string())
+            );
+        }
         match status {
             StatusCode::OK => {
-                let json: Value = response.json().await?;
-                let status = json["status"].as_str().unwrap_or("failed").to_string();
-                let retcode = json["retcode"].as_str().unwrap_or("0").to_string();
-                if status == "failed" {
-                    return Err(anyhow::anyhow!(
-                        "http call api unknown error, status: 'failed', raw resp: {}",
-                        json.to_string()
-                    )
-                    .into());
-                }
-                let data = match status.as_str() {
-                    "ok" => match ApiRespData::from_resp_type(resp_type, json["data"].clone()) {
-                        Ok(resp) => Ok(resp),
-                        Err(e) => {
-                            warn!(
-                                "http call api failed, raw resp: {}, raw req: {}",
-                                json,
-                                serde_json::to_string(&api_data)
-                                    .unwrap_or("Serialize Failed".to_string())
-                            );
-                            Err(e)
-                        }
-                    }?,
-                    _ => ApiRespData::NoResponse(None),
-                };
-                Ok(HttpCallApiResp {
-                    status,
-                    retcode,
-                    data,
+                serde_json::from_str(&text).map_err(|error| ApiError::Decode { error, raw: text })
+            }
+            StatusCode::UNAUTHORIZED => {
+                Err(ApiError::Auth("access token not provided".to_string()))
+            }
+            StatusCode::FORBIDDEN => Err(ApiError::Auth("access token mismatch".to_string())),
+            StatusCode::BAD_REQUEST => Err(ApiError::Failed {
+                retcode: RetCode::BadRequest,
+                message: "invalid request body".to_string(),
+                wording: text,
+            }),
+            StatusCode::NOT_FOUND => Err(ApiError::Failed {
+                retcode: RetCode::ApiNotFound,
+                message: "api not found".to_string(),
+                wording: text,
+            }),
+            _ => Err(ApiError::Transport(anyhow::anyhow!(
+                "http call api failed with status {}",
+                status
+            ))),
+        }
+    }
+}
+
+#[async_trait]
+impl OneBotClient for HttpConnect {
+    async fn call_api_raw(
+        &self,
+        api_data: ApiPayload,
+        options: CallOptions,
+    ) -> Result<ApiRespBuilder, ApiError> {
+        HttpConnect::call_api_raw(self, api_data, options).await
+    }
+
+    async fn subscribe(&self) -> Option<EventReceiver> {
+        match self.config.poll {
+            Some(_) => Some(HttpConnect::subscribe(self).await),
+            None => None,
+        }
+    }
+
+    async fn shutdown(&self) {
+        HttpConnect::shutdown(self).await
+    }
+}
+
+impl HttpConfig {
+    fn url(&self, action: &str) -> String {
+        format!(
+            "{}://{}/{}",
+            if self.tls.is_some() { "https" } else { "http" },
+            authority(&self.host, self.port),
+            action
+        )
+    }
+
+    /// 按 `token_placement` 为请求附加 access token
+    fn authorize(&self, mut request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        if let Some(token) = &self.access_token {
+            if self.token_placement.header() {
+                request = request.bearer_auth(token);
+            }
+            if self.token_placement.query() {
+                request = request.query(&[("access_token", token)]);
+            }
+        }
+        request
+    }
+}
+
+/// 隐藏错误信息中 URL 携带的 access token
+fn redact_url(mut e: reqwest::Error) -> reqwest::Error {
+    if let Some(url) = e.url_mut() {
+        if url.query_pairs().any(|(key, _)| key == "access_token") {
+            let pairs: Vec<(String, String)> = url
+                .query_pairs()
+                .map(|(key, value)| {
+                    let value = match key.as_ref() {
+                        "access_token" => redact(Some(&value)).to_string(),
+                        _ => value.into_owned(),
+                    };
+                    (key.into_owned(), value)
                 })
+                .collect();
+            url.query_pairs_mut().clear().extend_pairs(pairs);
+        }
+    }
+    e
+}
+
+/// 长轮询任务, `HttpConnect` 被关闭或释放后退出
+async fn poll_events(
+    client: reqwest::Client,
+    config: HttpConfig,
+    poll: HttpPollConfig,
+    events: Weak<EventChannel>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    info!("HTTP long polling started, action: {}", poll.action);
+    let url = config.url(&poll.action);
+    let body = serde_json::json!({
+        "limit": poll.limit,
+        "timeout": poll.timeout.as_secs(),
+    });
+    'poll: loop {
+        if events.strong_count() == 0 {
+            break;
+        }
+        let request = config
+            .authorize(client.post(&url))
+            .json(&body)
+            .timeout(poll.timeout + config.api_timeout);
+        let request = async {
+            match request.send().await {
+                Ok(resp) if resp.status() == StatusCode::OK => resp
+                    .json::<ApiRespBuilder>()
+                    .await
+                    .map_err(|e| anyhow::Error::from(redact_url(e)))
+                    .and_then(|resp| Ok(resp.decode::<Vec<Value>>()?)),
+                Ok(resp) => Err(anyhow::anyhow!("HTTP status {}", resp.status())),
+                Err(e) => Err(redact_url(e).into()),
             }
-            _ => {
-                if let Ok(resp_str) = response.text().await {
-                    warn!(
-                        "http call api failed, raw resp: {}, raw req: {}",
-                        resp_str,
-                        serde_json::to_string(&api_data).unwrap_or("Serialize Failed".to_string())
-                    );
+        };
+        let result = tokio::select! {
+            result = request => result,
+            _ = shutdown_signal(&mut shutdown) => break,
+        };
+        let raw_events = match result {
+            Ok(raw_events) => raw_events,
+            Err(e) => {
+                warn!(
+                    "HTTP long polling failed: {}, will retry in {:?}",
+                    e, poll.retry_delay
+                );
+                tokio::select! {
+                    _ = sleep(poll.retry_delay) => continue,
+                    _ = shutdown_signal(&mut shutdown) => break,
+                }
+            }
+        };
+        let Some(channel) = events.upgrade() else {
+            break;
+        };
+        for raw in raw_events {
+            match serde_json::from_value::<Event>(raw.clone()) {
+                Ok(Event::ApiRespBuilder(_)) | Err(_) => {
+                    warn!("Error parsing polled Event, Raw: {}", raw);
                 }
-                return Err(HttpCallApiError::from(status));
+                // 队列已满时暂停轮询, 等待订阅者取走事件
+                Ok(event) => tokio::select! {
+                    _ = channel.send(event) => {}
+                    _ = shutdown_signal(&mut shutdown) => break 'poll,
+                },
             }
         }
     }
+    info!("HTTP long polling stopped");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::meta::Meta;
+    use http_body_util::{BodyExt as _, Full};
+    use hyper::body::{Bytes, Incoming};
+    use hyper::server::conn::http1;
+    use hyper::service::service_fn;
+    use hyper::{Request, Response};
+    use hyper_util::rt::TokioIo;
+    use std::convert::Infallible;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+    use tokio::time::timeout;
+
+    /// 模拟长轮询接口: 第一次请求返回 500, 第二次返回两个心跳事件, 之后一直没有新事件
+    async fn polling_server() -> (u16, mpsc::UnboundedReceiver<(String, Value)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (requests_tx, requests) = mpsc::unbounded_channel();
+        let count = Arc::new(AtomicUsize::new(0));
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let requests_tx = requests_tx.clone();
+                let count = count.clone();
+                let service = service_fn(move |req: Request<Incoming>| {
+                    let requests_tx = requests_tx.clone();
+                    let count = count.clone();
+                    async move {
+                        let path = req.uri().path().to_string();
+                        let body = req.into_body().collect().await.unwrap().to_bytes();
+                        let _ = requests_tx.send((path, serde_json::from_slice(&body).unwrap()));
+                        let body = match count.fetch_add(1, Ordering::Relaxed) {
+                            0 => {
+                                let mut resp = Response::new(Full::new(Bytes::new()));
+                                *resp.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+                                return Ok::<_, Infallible>(resp);
+                            }
+                            1 => {
+                                let events = [0, 1].map(|time| {
+                                    serde_json::json!({
+                                        "time": time,
+                                        "self_id": 10001,
+                                        "post_type": "meta_event",
+                                        "meta_event_type": "heartbeat",
+                                        "status": {"online": true, "good": true},
+                                        "interval": 5000,
+                                    })
+                                });
+                                serde_json::json!({"status": "ok", "retcode": 0, "data": events})
+                            }
+                            _ => std::future::pending().await,
+                        };
+                        Ok(Response::new(Full::new(Bytes::from(body.to_string()))))
+                    }
+                });
+                tokio::spawn(http1::Builder::new().serve_connection(TokioIo::new(stream), service));
+            }
+        });
+        (port, requests)
+    }
+
+    #[tokio::test]
+    async fn poll_events_retries_and_delivers_events() {
+        let (port, mut requests) = polling_server().await;
+        let connect = HttpConnect::new(HttpConfig {
+            port,
+            poll: Some(HttpPollConfig {
+                limit: 10,
+                timeout: Duration::from_secs(5),
+                retry_delay: Duration::from_millis(50),
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+        .unwrap();
+        let mut events = connect.subscribe().await;
+
+        // 第一次失败后重试, 每次请求都携带 limit 与 timeout
+        for _ in 0..3 {
+            let (path, body) = timeout(Duration::from_secs(5), requests.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(path, "/get_latest_events");
+            assert_eq!(body, serde_json::json!({"limit": 10, "timeout": 5}));
+        }
+        for time in 0..2 {
+            let event = timeout(Duration::from_secs(5), events.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            assert!(
+                matches!(event, Event::Meta(Meta::Heartbeat(heartbeat)) if heartbeat.time == time)
+            );
+        }
+
+        // 第三次请求挂起时关闭, 之后不再发出请求
+        timeout(Duration::from_secs(5), connect.shutdown())
+            .await
+            .expect("shutdown should stop the poller");
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(requests.try_recv().is_err());
+    }
+
+    #[test]
+    fn url_brackets_ipv6_host() {
+        let config = HttpConfig {
+            host: "::1".to_string(),
+            port: 5700,
+            ..Default::default()
+        };
+        assert_eq!(config.url("get_status"), "http://[::1]:5700/get_status");
+    }
+
+    #[tokio::test]
+    async fn redact_url_hides_access_token() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let url = format!(
+            "http://127.0.0.1:{}/get_status?access_token=secret&foo=bar",
+            port
+        );
+        let e = redact_url(reqwest::Client::new().post(url).send().await.unwrap_err());
+        let url = e.url().unwrap();
+        assert_eq!(
+            url.query_pairs().collect::<Vec<_>>(),
+            [
+                ("access_token".into(), "<redacted>".into()),
+                ("foo".into(), "bar".into())
+            ]
+        );
+        assert!(!e.to_string().contains("secret"));
+        assert!(matches!(ApiError::from(e), ApiError::NotSent(_)));
+    }
+
+    #[test]
+    fn new_without_runtime() {
+        assert!(HttpConnect::new(HttpConfig::default()).is_ok());
+
+        let config = HttpConfig {
+            poll: Some(HttpPollConfig::default()),
+            ..Default::default()
+        };
+        let err = HttpConnect::new(config).err().unwrap();
+        assert!(err.to_string().contains("Tokio runtime"));
+    }
 }
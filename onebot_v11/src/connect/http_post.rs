@@ -0,0 +1,314 @@
+use super::channel::{EventChannel, EventChannelConfig, EventReceiver};
+use super::shutdown_signal;
+use crate::event::QuickOperation;
+use crate::Event;
+use futures_util::future::BoxFuture;
+use hmac::{Hmac, Mac};
+use http_body_util::{BodyExt as _, Full, LengthLimitError, Limited};
+use hyper::body::{Bytes, Incoming};
+use hyper::header::CONTENT_TYPE;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use sha1::Sha1;
+use std::convert::Infallible;
+use std::future::Future;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::{watch, RwLock};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+type HmacSha1 = Hmac<Sha1>;
+
+type QuickOperationHandler =
+    Arc<dyn Fn(Event) -> BoxFuture<'static, Option<QuickOperation>> + Send + Sync>;
+
+/// HTTP POST 上报配置, 对应 OneBot 实现中的 HTTP POST 上报地址
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HttpPostConfig {
+    pub host: String,
+    pub port: u16,
+    /// 上报路径后缀, 为空时接受任意路径
+    pub suffix: String,
+    /// 上报签名密钥, 设置后会校验 `X-Signature` 头
+    pub secret: Option<String>,
+    /// 请求体的最大长度, 超出时返回 413
+    pub max_body_size: usize,
+    /// 事件通道容量与模式
+    pub events: EventChannelConfig,
+}
+
+impl Default for HttpPostConfig {
+    fn default() -> Self {
+        HttpPostConfig {
+            host: "127.0.0.1".to_string(),
+            port: 8082,
+            suffix: "".to_string(),
+            secret: None,
+            max_body_size: 4 * 1024 * 1024,
+            events: EventChannelConfig::default(),
+        }
+    }
+}
+
+pub struct HttpPostConnect {
+    pub config: HttpPostConfig,
+    events: EventChannel,
+    quick_operation_handler: RwLock<Option<QuickOperationHandler>>,
+    shutdown: watch::Sender<bool>,
+    task: std::sync::Mutex<Option<JoinHandle<()>>>,
+}
+
+impl HttpPostConnect {
+    pub async fn new(config: HttpPostConfig) -> Result<Arc<Self>, anyhow::Error> {
+        let listener = TcpListener::bind(format!("{}:{}", config.host, config.port)).await?;
+        info!(
+            "HTTP POST server listening on {}:{}",
+            config.host, config.port
+        );
+        let events = EventChannel::new(&config.events);
+        let self_ = Arc::new(Self {
+            config,
+            events,
+            quick_operation_handler: RwLock::new(None),
+            shutdown: watch::channel(false).0,
+            task: std::sync::Mutex::new(None),
+        });
+
+        let task = tokio::spawn(self_.clone().run(listener));
+        *self_.task.lock().unwrap() = Some(task);
+        Ok(self_)
+    }
+
+    /// 接受连接直到调用 `shutdown()`, 之后等待所有连接处理完当前请求
+    async fn run(self: Arc<Self>, listener: TcpListener) {
+        let mut shutdown = self.shutdown.subscribe();
+        let mut connections: Vec<JoinHandle<()>> = Vec::new();
+        loop {
+            let accepted = tokio::select! {
+                accepted = listener.accept() => accepted,
+                _ = shutdown_signal(&mut shutdown) => break,
+            };
+            let stream = match accepted {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    warn!("Error accepting HTTP POST connection: {}", e);
+                    continue;
+                }
+            };
+            let self_clone = self.clone();
+            let mut shutdown = shutdown.clone();
+            connections.retain(|connection| !connection.is_finished());
+            connections.push(tokio::spawn(async move {
+                let service = service_fn(move |req| self_clone.clone().handle(req));
+                let connection =
+                    http1::Builder::new().serve_connection(TokioIo::new(stream), service);
+                tokio::pin!(connection);
+                let result = tokio::select! {
+                    result = connection.as_mut() => result,
+                    _ = shutdown_signal(&mut shutdown) => {
+                        connection.as_mut().graceful_shutdown();
+                        connection.await
+                    }
+                };
+                if let Err(e) = result {
+                    warn!("Error serving HTTP POST connection: {}", e);
+                }
+            }));
+        }
+        drop(listener);
+        for connection in connections {
+            if let Err(e) = connection.await {
+                warn!("Error joining HTTP POST connection task: {}", e);
+            }
+        }
+        info!("HTTP POST server shut down");
+    }
+
+    /// 关闭服务端: 停止接受新连接, 等待正在处理的上报完成后退出
+    pub async fn shutdown(&self) {
+        self.shutdown.send_replace(true);
+        let task = self.task.lock().unwrap().take();
+        if let Some(task) = task {
+            if let Err(e) = task.await {
+                warn!("Error joining HTTP POST task: {}", e);
+            }
+        }
+    }
+
+    async fn handle(
+        self: Arc<Self>,
+        req: Request<Incoming>,
+    ) -> Result<Response<Full<Bytes>>, Infallible> {
+        if req.method() != Method::POST {
+            return Ok(empty_response(StatusCode::METHOD_NOT_ALLOWED));
+        }
+        let path = req.uri().path().trim_end_matches('/');
+        if !path.ends_with(&self.config.suffix) {
+            return Ok(empty_response(StatusCode::NOT_FOUND));
+        }
+        let signature = req
+            .headers()
+            .get("X-Signature")
+            .map(|v| v.to_str().unwrap_or("").to_string());
+        let body = match Limited::new(req.into_body(), self.config.max_body_size)
+            .collect()
+            .await
+        {
+            Ok(body) => body.to_bytes(),
+            Err(e) if e.is::<LengthLimitError>() => {
+                warn!("HTTP POST body exceeds {} bytes", self.config.max_body_size);
+                return Ok(empty_response(StatusCode::PAYLOAD_TOO_LARGE));
+            }
+            Err(e) => {
+                warn!("Error reading HTTP POST body: {}", e);
+                return Ok(empty_response(StatusCode::BAD_REQUEST));
+            }
+        };
+
+        if let Some(secret) = &self.config.secret {
+            match signature {
+                Some(signature) => {
+                    if !verify_signature(secret, &body, &signature) {
+                        warn!("HTTP POST signature mismatch");
+                        return Ok(empty_response(StatusCode::FORBIDDEN));
+                    }
+                }
+                None => {
+                    warn!("HTTP POST missing X-Signature");
+                    return Ok(empty_response(StatusCode::UNAUTHORIZED));
+                }
+            }
+        }
+
+        match serde_json::from_slice::<Event>(&body) {
+            Ok(Event::ApiRespBuilder(_)) => {
+                warn!(
+                    "Error parsing Event: missing post_type, Raw: {}",
+                    String::from_utf8_lossy(&body)
+                );
+                Ok(empty_response(StatusCode::BAD_REQUEST))
+            }
+            Ok(event) => {
+                let handler = self.quick_operation_handler.read().await.clone();
+                // 队列已满时保持请求等待订阅者取走事件, 关闭时放弃
+                let mut shutdown = self.shutdown.subscribe();
+                tokio::select! {
+                    _ = self.events.send(event.clone()) => {}
+                    _ = shutdown_signal(&mut shutdown) => {
+                        return Ok(empty_response(StatusCode::SERVICE_UNAVAILABLE));
+                    }
+                }
+                let operation = match handler {
+                    Some(handler) => handler(event).await,
+                    None => None,
+                };
+                match operation.map(|op| serde_json::to_vec(&op)) {
+                    Some(Ok(body)) => {
+                        let mut resp = Response::new(Full::new(Bytes::from(body)));
+                        resp.headers_mut()
+                            .insert(CONTENT_TYPE, "application/json".parse().unwrap());
+                        Ok(resp)
+                    }
+                    Some(Err(e)) => {
+                        warn!("Error serializing QuickOperation: {}", e);
+                        Ok(empty_response(StatusCode::NO_CONTENT))
+                    }
+                    None => Ok(empty_response(StatusCode::NO_CONTENT)),
+                }
+            }
+            Err(e) => {
+                warn!(
+                    "Error parsing Event: {}, Raw: {}",
+                    e,
+                    String::from_utf8_lossy(&body)
+                );
+                Ok(empty_response(StatusCode::BAD_REQUEST))
+            }
+        }
+    }
+
+    pub async fn subscribe(&self) -> EventReceiver {
+        self.events.subscribe()
+    }
+
+    /// 订阅者因处理过慢而丢失的事件总数
+    pub fn dropped_events(&self) -> u64 {
+        self.events.dropped_events()
+    }
+
+    /// 设置快速操作处理函数, 每个上报的事件都会交给它处理,
+    /// 返回的快速操作会作为该次上报的响应体返回给 OneBot 实现
+    pub async fn set_quick_operation_handler<F, Fut>(&self, handler: F)
+    where
+        F: Fn(Event) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Option<QuickOperation>> + Send + 'static,
+    {
+        let handler: QuickOperationHandler = Arc::new(move |event| Box::pin(handler(event)));
+        *self.quick_operation_handler.write().await = Some(handler);
+    }
+}
+
+/// 校验 `X-Signature: sha1=<hex>`, 签名为以 secret 为密钥对请求体计算的 HMAC-SHA1
+fn verify_signature(secret: &str, body: &[u8], signature: &str) -> bool {
+    let Some(signature) = signature.strip_prefix("sha1=") else {
+        return false;
+    };
+    let Ok(signature) = hex::decode(signature) else {
+        return false;
+    };
+    let Ok(mut mac) = HmacSha1::new_from_slice(secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(body);
+    mac.verify_slice(&signature).is_ok()
+}
+
+fn empty_response(status: StatusCode) -> Response<Full<Bytes>> {
+    let mut resp = Response::new(Full::new(Bytes::new()));
+    *resp.status_mut() = status;
+    resp
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sign(secret: &str, body: &[u8]) -> String {
+        let mut mac = HmacSha1::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(body);
+        format!("sha1={}", hex::encode(mac.finalize().into_bytes()))
+    }
+
+    #[test]
+    fn verify_signature_accepts_valid_signature() {
+        let body = br#"{"post_type":"message"}"#;
+        assert!(verify_signature("secret", body, &sign("secret", body)));
+    }
+
+    #[test]
+    fn verify_signature_rejects_invalid_signature() {
+        let body = br#"{"post_type":"message"}"#;
+        assert!(!verify_signature("secret", body, &sign("other", body)));
+        assert!(!verify_signature(
+            "secret",
+            b"tampered",
+            &sign("secret", body)
+        ));
+    }
+
+    #[test]
+    fn verify_signature_rejects_malformed_header() {
+        let body = b"{}";
+        let signature = sign("secret", body);
+        assert!(!verify_signature(
+            "secret",
+            body,
+            signature.trim_start_matches("sha1=")
+        ));
+        assert!(!verify_signature("secret", body, "sha1=not-hex"));
+        assert!(!verify_signature("secret", body, ""));
+    }
+}
@@ -1,12 +1,28 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use tokio::sync::broadcast;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::{oneshot, watch};
+use tokio::time::{sleep_until, timeout, Instant, Interval};
+use tokio_tungstenite::tungstenite::protocol::Message;
+use tracing::{debug, warn};
 
+use crate::api::error::ApiError;
 use crate::api::payload::ApiPayload;
-use crate::api::resp::ApiRespBuilder;
+use crate::api::resp::{ApiRespBuilder, GetStatusResponse};
+use crate::event::meta::Heartbeat;
 use crate::traits::EndPoint;
+use crate::Event;
 
+pub mod auth;
+pub mod channel;
+pub mod failover;
 pub mod http;
+pub mod http_post;
+pub mod proxy;
+pub mod tls;
 pub mod ws;
 pub mod ws_reverse;
 use rand::distributions::Alphanumeric;
@@ -19,11 +35,17 @@ pub struct WsApiPayload {
     pub echo: String,
 }
 
-impl Into<WsApiPayload> for ApiPayload {
-    fn into(self) -> WsApiPayload {
+impl From<ApiPayload> for WsApiPayload {
+    fn from(payload: ApiPayload) -> Self {
+        WsApiPayload::new(payload, CallMode::Normal)
+    }
+}
+
+impl WsApiPayload {
+    pub fn new(payload: ApiPayload, mode: CallMode) -> Self {
         WsApiPayload {
-            action: self.endpoint(),
-            params: serde_json::to_value(self).unwrap(),
+            action: mode.action(&payload.endpoint()),
+            params: serde_json::to_value(payload).unwrap(),
             echo: thread_rng()
                 .sample_iter(&Alphanumeric)
                 .take(10)
@@ -33,16 +55,142 @@ impl Into<WsApiPayload> for ApiPayload {
     }
 }
 
-async fn get_resp_builder(
-    mut subscriber: broadcast::Receiver<ApiRespBuilder>,
+type PendingSender = oneshot::Sender<Result<ApiRespBuilder, String>>;
+
+/// 等待响应的 API 调用, 以 echo 为键, 响应直接交给对应的调用方
+#[derive(Default)]
+pub(crate) struct PendingRequests {
+    inner: Mutex<HashMap<String, PendingSender>>,
+}
+
+impl PendingRequests {
+    /// 在发送请求之前注册 echo, 保证不会错过响应
+    pub(crate) fn register(&self, echo: String) -> PendingCall<'_> {
+        let (sender, receiver) = oneshot::channel();
+        self.inner.lock().unwrap().insert(echo.clone(), sender);
+        PendingCall {
+            pending: self,
+            echo,
+            receiver,
+        }
+    }
+
+    /// 将响应交给对应的调用方, 没有等待该 echo 的调用时返回 `false`
+    pub(crate) fn resolve(&self, resp: ApiRespBuilder) -> bool {
+        let sender = self.inner.lock().unwrap().remove(&resp.echo);
+        match sender {
+            Some(sender) => sender.send(Ok(resp)).is_ok(),
+            None => false,
+        }
+    }
+
+    /// 连接断开时令所有等待中的调用立即失败
+    pub(crate) fn fail_all(&self, reason: &str) {
+        for (_, sender) in self.inner.lock().unwrap().drain() {
+            let _ = sender.send(Err(reason.to_string()));
+        }
+    }
+}
+
+/// 一次已注册的 API 调用, 被丢弃（超时、发送失败或调用方取消）时移除对应的 echo
+pub(crate) struct PendingCall<'a> {
+    pending: &'a PendingRequests,
     echo: String,
-) -> Option<ApiRespBuilder> {
-    while let Ok(resp) = subscriber.recv().await {
-        if resp.echo == echo {
-            return Some(resp);
+    receiver: oneshot::Receiver<Result<ApiRespBuilder, String>>,
+}
+
+impl PendingCall<'_> {
+    /// 在 `duration` 内完成发送与等待响应, 写锁被占用或对方不读取导致的发送阻塞同样计入超时
+    pub(crate) async fn send_and_wait(
+        self,
+        duration: Duration,
+        send: impl Future<Output = Result<(), ApiError>>,
+    ) -> Result<ApiRespBuilder, ApiError> {
+        let call = async {
+            send.await?;
+            self.wait(duration).await
+        };
+        timeout(duration, call)
+            .await
+            .unwrap_or(Err(ApiError::Timeout(duration)))
+    }
+
+    pub(crate) async fn wait(mut self, duration: Duration) -> Result<ApiRespBuilder, ApiError> {
+        match timeout(duration, &mut self.receiver).await {
+            Ok(Ok(Ok(resp))) => Ok(resp),
+            Ok(Ok(Err(reason))) => Err(ApiError::Transport(anyhow::anyhow!(
+                "Connection closed before API response: {}",
+                reason
+            ))),
+            Ok(Err(_)) => Err(ApiError::Transport(anyhow::anyhow!(
+                "Connection closed before API response"
+            ))),
+            Err(_) => Err(ApiError::Timeout(duration)),
         }
     }
-    None
+}
+
+impl Drop for PendingCall<'_> {
+    fn drop(&mut self) {
+        self.pending.inner.lock().unwrap().remove(&self.echo);
+    }
+}
+
+/// 单次 API 调用的选项, 未设置的项使用连接配置中的默认值
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct CallOptions {
+    /// 等待响应的超时时间, 覆盖配置中的 `api_timeout`
+    pub timeout: Option<Duration>,
+    /// 仅反向 ws 使用, 指定通过哪个机器人的连接调用, 为空时使用任意一个可调用 API 的连接
+    pub bot_id: Option<String>,
+    /// 调用方式, 决定 API 名称的后缀
+    pub mode: CallMode,
+}
+
+/// API 调用方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum CallMode {
+    /// 同步调用, 等待操作完成后响应
+    #[default]
+    Normal,
+    /// 在 API 名称后加 `_async`, OneBot 实现收到后立即响应 `status: "async"`,
+    /// 无法得知操作结果
+    Async,
+    /// 在 API 名称后加 `_rate_limited`, 由 OneBot 实现排队限速执行, 同样立即响应
+    RateLimited,
+}
+
+impl CallMode {
+    /// 按调用方式得到实际的 API 名称
+    pub fn action(&self, endpoint: &str) -> String {
+        match self {
+            CallMode::Normal => endpoint.to_string(),
+            CallMode::Async => format!("{}_async", endpoint),
+            CallMode::RateLimited => format!("{}_rate_limited", endpoint),
+        }
+    }
+}
+
+/// access token 的传递方式, 反向 ws 服务端按此决定从何处读取 token
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum TokenPlacement {
+    /// `Authorization: Bearer <token>` 头
+    #[default]
+    Header,
+    /// `?access_token=<token>` 查询参数
+    Query,
+    /// 客户端同时使用两种方式发送, 服务端接受任意一种
+    Both,
+}
+
+impl TokenPlacement {
+    pub fn header(&self) -> bool {
+        matches!(self, TokenPlacement::Header | TokenPlacement::Both)
+    }
+
+    pub fn query(&self) -> bool {
+        matches!(self, TokenPlacement::Query | TokenPlacement::Both)
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
@@ -52,6 +200,7 @@ pub enum WsType {
     Universal,
 }
 impl WsType {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Self {
         match s.to_lowercase().as_str() {
             "event" => WsType::Event,
@@ -61,3 +210,351 @@ impl WsType {
         }
     }
 }
+
+/// 连接状态, 通过 `state()` 获取的 `watch::Receiver` 观察变化
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// 正在建立首次连接, 反向 ws 为等待 OneBot 实现连接
+    Connecting,
+    /// 已连接, 包含机器人 QQ 号（如已知）与连接类型.
+    /// 正向 ws 连接时使用配置的 `bot_id`, 收到第一个事件后更新为事件中的 `self_id`
+    Connected(Option<String>, WsType),
+    /// 连接已断开, 包含断开原因
+    Disconnected(String),
+    /// 正在进行第 n 次重连
+    Reconnecting(u32),
+    /// 已通过 `shutdown()` 关闭, 不会再重连
+    Closed,
+}
+
+impl ConnectionState {
+    pub fn is_connected(&self) -> bool {
+        matches!(self, ConnectionState::Connected(..))
+    }
+}
+
+/// 断线重连策略
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ReconnectPolicy {
+    /// 第一次失败后的等待时间
+    pub initial_delay: Duration,
+    /// 每次失败后等待时间乘以的倍数, 为 1 时即固定间隔
+    pub multiplier: u32,
+    /// 在等待时间上附加的随机抖动上限
+    pub jitter: Duration,
+    /// 等待时间上限（不含抖动）
+    pub max_delay: Duration,
+    /// 最大连接尝试次数（包含第一次）, `None` 表示无限重试
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        ReconnectPolicy {
+            initial_delay: Duration::from_secs(1),
+            multiplier: 2,
+            jitter: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            max_attempts: None,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// 第 `attempt` 次尝试失败后是否继续重试, `attempt` 从 1 开始
+    pub fn should_retry(&self, attempt: u32) -> bool {
+        self.max_attempts.is_none_or(|max| attempt < max)
+    }
+
+    /// 第 `attempt` 次尝试失败后的等待时间, `attempt` 从 1 开始
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = self
+            .multiplier
+            .checked_pow(attempt.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        let delay = self
+            .initial_delay
+            .checked_mul(factor)
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay);
+        let jitter = self.jitter.as_millis() as u64;
+        if jitter == 0 {
+            delay
+        } else {
+            delay + Duration::from_millis(thread_rng().gen_range(0..=jitter))
+        }
+    }
+}
+
+/// 心跳看门狗配置, 根据 `Meta::Heartbeat` 中的 `interval` 判断连接是否存活,
+/// 收到第一个心跳事件后开始计时
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HeartbeatConfig {
+    /// 连续错过多少次心跳后认为连接已断开
+    pub missed_beats: u32,
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        HeartbeatConfig { missed_beats: 3 }
+    }
+}
+
+/// 单条连接上的心跳看门狗, 未配置时只记录心跳中的状态, 不会超时
+pub(crate) struct HeartbeatWatchdog {
+    config: Option<HeartbeatConfig>,
+    interval: Option<Duration>,
+    last_beat: Instant,
+}
+
+impl HeartbeatWatchdog {
+    pub(crate) fn new(config: Option<HeartbeatConfig>) -> Self {
+        HeartbeatWatchdog {
+            config,
+            interval: None,
+            last_beat: Instant::now(),
+        }
+    }
+
+    /// 记录一次心跳, 返回心跳中的运行状态
+    pub(crate) fn beat(&mut self, heartbeat: &Heartbeat) -> Option<GetStatusResponse> {
+        self.last_beat = Instant::now();
+        if heartbeat.interval > 0 {
+            self.interval = Some(Duration::from_millis(heartbeat.interval as u64));
+        }
+        match serde_json::from_value(heartbeat.status.clone()) {
+            Ok(status) => Some(status),
+            Err(e) => {
+                warn!(
+                    "Error parsing heartbeat status: {}, Raw: {}",
+                    e, heartbeat.status
+                );
+                None
+            }
+        }
+    }
+
+    /// 超过允许错过的心跳次数时返回
+    pub(crate) async fn expired(&self) -> Duration {
+        match (&self.config, self.interval) {
+            (Some(config), Some(interval)) => {
+                let timeout = interval * config.missed_beats.max(1);
+                sleep_until(self.last_beat + timeout).await;
+                timeout
+            }
+            _ => std::future::pending().await,
+        }
+    }
+}
+
+/// 收到的 ws 帧的处理结果
+#[allow(clippy::large_enum_variant)]
+pub(crate) enum WsFrame {
+    /// 文本帧或 UTF-8 二进制帧中的事件或 API 响应
+    Event(Event),
+    /// 对方发送的关闭帧, 包含关闭码与原因
+    Close(String),
+    /// 控制帧或无法解析的帧, 无需处理
+    Skip,
+}
+
+impl From<Message> for WsFrame {
+    fn from(msg: Message) -> Self {
+        let text = match msg {
+            Message::Text(text) => text,
+            Message::Binary(data) => match String::from_utf8(data) {
+                Ok(text) => text,
+                Err(e) => {
+                    warn!("Error decoding binary WsMessage as UTF-8: {}", e);
+                    return WsFrame::Skip;
+                }
+            },
+            // tungstenite 会自动回复 Pong, 并在下一次读写时发送
+            Message::Ping(_) | Message::Pong(_) | Message::Frame(_) => {
+                debug!("Received control frame: {:?}", msg);
+                return WsFrame::Skip;
+            }
+            Message::Close(frame) => {
+                return WsFrame::Close(match frame {
+                    Some(frame) => format!(
+                        "Closed by peer, code: {}, reason: {}",
+                        frame.code, frame.reason
+                    ),
+                    None => "Closed by peer".to_string(),
+                })
+            }
+        };
+        match serde_json::from_str::<Event>(&text) {
+            Ok(event) => WsFrame::Event(event),
+            Err(e) => {
+                warn!("Error parsing Event: {}, Raw: {}", e, text);
+                WsFrame::Skip
+            }
+        }
+    }
+}
+
+/// 等待下一次定时发送 Ping, 未设置时永不返回
+pub(crate) async fn next_ping(interval: &mut Option<Interval>) {
+    match interval {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// 等待关闭信号, 发送端被释放时同样视为关闭
+pub(crate) async fn shutdown_signal(shutdown: &mut watch::Receiver<bool>) {
+    let _ = shutdown.wait_for(|closed| *closed).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(multiplier: u32) -> ReconnectPolicy {
+        ReconnectPolicy {
+            initial_delay: Duration::from_secs(1),
+            multiplier,
+            jitter: Duration::ZERO,
+            max_delay: Duration::from_secs(30),
+            max_attempts: None,
+        }
+    }
+
+    #[test]
+    fn reconnect_delay_backs_off_up_to_max() {
+        let policy = policy(2);
+        assert_eq!(policy.delay(1), Duration::from_secs(1));
+        assert_eq!(policy.delay(2), Duration::from_secs(2));
+        assert_eq!(policy.delay(5), Duration::from_secs(16));
+        assert_eq!(policy.delay(6), Duration::from_secs(30));
+        assert_eq!(policy.delay(u32::MAX), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn reconnect_delay_with_fixed_interval_and_jitter() {
+        assert_eq!(policy(1).delay(10), Duration::from_secs(1));
+
+        let policy = ReconnectPolicy {
+            jitter: Duration::from_millis(500),
+            ..policy(1)
+        };
+        for attempt in 1..20 {
+            let delay = policy.delay(attempt);
+            assert!(delay >= Duration::from_secs(1));
+            assert!(delay <= Duration::from_millis(1500));
+        }
+    }
+
+    #[test]
+    fn reconnect_should_retry_respects_max_attempts() {
+        assert!(policy(2).should_retry(u32::MAX));
+
+        let policy = ReconnectPolicy {
+            max_attempts: Some(3),
+            ..policy(2)
+        };
+        assert!(policy.should_retry(1));
+        assert!(policy.should_retry(2));
+        assert!(!policy.should_retry(3));
+    }
+
+    fn heartbeat(interval: i64) -> Heartbeat {
+        serde_json::from_value(serde_json::json!({
+            "time": 0,
+            "self_id": 10001,
+            "post_type": "meta_event",
+            "meta_event_type": "heartbeat",
+            "status": {"online": true, "good": false},
+            "interval": interval,
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn watchdog_expires_after_missed_beats() {
+        let mut watchdog = HeartbeatWatchdog::new(Some(HeartbeatConfig { missed_beats: 3 }));
+        // 收到第一个心跳前不计时
+        assert!(timeout(Duration::from_secs(3600), watchdog.expired())
+            .await
+            .is_err());
+
+        let status = watchdog.beat(&heartbeat(5000)).unwrap();
+        assert_eq!(
+            status,
+            GetStatusResponse {
+                online: Some(true),
+                good: false
+            }
+        );
+        assert!(timeout(Duration::from_secs(14), watchdog.expired())
+            .await
+            .is_err());
+        // 按时收到心跳后重新计时
+        watchdog.beat(&heartbeat(5000));
+        assert!(timeout(Duration::from_secs(14), watchdog.expired())
+            .await
+            .is_err());
+        let start = Instant::now();
+        assert_eq!(watchdog.expired().await, Duration::from_secs(15));
+        assert_eq!(start.elapsed(), Duration::from_secs(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn watchdog_without_config_never_expires() {
+        let mut watchdog = HeartbeatWatchdog::new(None);
+        assert!(watchdog.beat(&heartbeat(5000)).is_some());
+        assert!(timeout(Duration::from_secs(3600), watchdog.expired())
+            .await
+            .is_err());
+    }
+
+    fn response(echo: &str) -> ApiRespBuilder {
+        serde_json::from_value(serde_json::json!({
+            "status": "ok",
+            "retcode": 0,
+            "data": null,
+            "echo": echo,
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn pending_resolve_delivers_matching_response() {
+        let pending = PendingRequests::default();
+        let call = pending.register("1".to_string());
+        assert!(!pending.resolve(response("2")));
+        assert!(pending.resolve(response("1")));
+        let resp = call.wait(Duration::from_secs(1)).await.unwrap();
+        assert_eq!(resp.echo, "1");
+        assert!(pending.inner.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn pending_fail_all_fails_waiting_calls() {
+        let pending = PendingRequests::default();
+        let first = pending.register("1".to_string());
+        let second = pending.register("2".to_string());
+        pending.fail_all("closed");
+        for call in [first, second] {
+            let err = call.wait(Duration::from_secs(1)).await.unwrap_err();
+            assert!(matches!(err, ApiError::Transport(_)));
+            assert!(err.to_string().contains("closed"));
+        }
+    }
+
+    #[tokio::test]
+    async fn pending_call_is_removed_on_timeout_or_drop() {
+        let pending = PendingRequests::default();
+        let call = pending.register("1".to_string());
+        let err = call.wait(Duration::from_millis(10)).await.unwrap_err();
+        assert!(matches!(err, ApiError::Timeout(_)));
+        assert!(pending.inner.lock().unwrap().is_empty());
+
+        drop(pending.register("2".to_string()));
+        assert!(pending.inner.lock().unwrap().is_empty());
+        assert!(!pending.resolve(response("2")));
+    }
+}
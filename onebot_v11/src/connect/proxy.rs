@@ -0,0 +1,295 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine as _;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::Ipv6Addr;
+use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+use tokio::net::TcpStream;
+use tokio_socks::tcp::Socks5Stream;
+
+/// 出站代理配置, 正向 ws 与 http 共用
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ProxyConfig {
+    pub kind: ProxyKind,
+    pub host: String,
+    pub port: u16,
+    /// 代理认证, http 代理使用 Basic 认证, socks5 使用用户名/密码认证
+    pub auth: Option<ProxyAuth>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ProxyKind {
+    /// HTTP CONNECT 代理
+    Http,
+    /// SOCKS5 代理, 目标域名由代理解析
+    Socks5,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+pub struct ProxyAuth {
+    pub username: String,
+    pub password: String,
+}
+
+impl fmt::Debug for ProxyAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProxyAuth")
+            .field("username", &self.username)
+            .field("password", &"***")
+            .finish()
+    }
+}
+
+/// CONNECT 响应头的最大长度
+const MAX_CONNECT_RESPONSE: usize = 8192;
+
+impl ProxyConfig {
+    /// 构建 reqwest 代理
+    pub(crate) fn reqwest_proxy(&self) -> Result<reqwest::Proxy, anyhow::Error> {
+        Ok(reqwest::Proxy::all(self.url()?)?)
+    }
+
+    /// 代理地址, 包含认证信息
+    fn url(&self) -> Result<Url, anyhow::Error> {
+        let scheme = match self.kind {
+            ProxyKind::Http => "http",
+            ProxyKind::Socks5 => "socks5h",
+        };
+        let mut url = Url::parse(&format!(
+            "{}://{}",
+            scheme,
+            authority(&self.host, self.port)
+        ))?;
+        if let Some(auth) = &self.auth {
+            url.set_username(&auth.username)
+                .and_then(|_| url.set_password(Some(&auth.password)))
+                .map_err(|_| anyhow::anyhow!("Invalid proxy credentials"))?;
+        }
+        Ok(url)
+    }
+
+    /// 通过代理建立到目标地址的 TCP 隧道
+    pub(crate) async fn connect(&self, host: &str, port: u16) -> Result<TcpStream, anyhow::Error> {
+        let proxy = (self.host.as_str(), self.port);
+        match self.kind {
+            ProxyKind::Http => {
+                let mut stream = TcpStream::connect(proxy).await?;
+                let target = authority(host, port);
+                let mut request = format!("CONNECT {} HTTP/1.1\r\nHost: {}\r\n", target, target);
+                if let Some(auth) = &self.auth {
+                    let credentials =
+                        STANDARD.encode(format!("{}:{}", auth.username, auth.password));
+                    request.push_str(&format!("Proxy-Authorization: Basic {}\r\n", credentials));
+                }
+                request.push_str("\r\n");
+                stream.write_all(request.as_bytes()).await?;
+
+                // 逐字节读取响应头, 避免读走隧道中的数据
+                let mut response = Vec::new();
+                while !response.ends_with(b"\r\n\r\n") {
+                    if response.len() >= MAX_CONNECT_RESPONSE {
+                        return Err(anyhow::anyhow!("Proxy CONNECT response too large"));
+                    }
+                    let byte = stream.read_u8().await?;
+                    response.push(byte);
+                }
+                let response = String::from_utf8_lossy(&response);
+                let status_line = response.lines().next().unwrap_or_default();
+                match status_line.split_whitespace().nth(1) {
+                    Some("200") => Ok(stream),
+                    _ => Err(anyhow::anyhow!(
+                        "Proxy CONNECT to {} failed: {}",
+                        target,
+                        status_line
+                    )),
+                }
+            }
+            ProxyKind::Socks5 => {
+                let stream = match &self.auth {
+                    Some(auth) => {
+                        Socks5Stream::connect_with_password(
+                            proxy,
+                            (host, port),
+                            &auth.username,
+                            &auth.password,
+                        )
+                        .await?
+                    }
+                    None => Socks5Stream::connect(proxy, (host, port)).await?,
+                };
+                Ok(stream.into_inner())
+            }
+        }
+    }
+}
+
+/// `host:port`, IPv6 地址加上方括号
+pub(crate) fn authority(host: &str, port: u16) -> String {
+    match host.parse::<Ipv6Addr>() {
+        Ok(_) => format!("[{}]:{}", host, port),
+        Err(_) => format!("{}:{}", host, port),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    /// 最简单的 HTTP CONNECT 代理: 记录请求头并以 `status` 响应, 200 时回显隧道中的数据
+    async fn connect_proxy(status: &'static str) -> (u16, tokio::task::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let task = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            while !request.ends_with(b"\r\n\r\n") {
+                request.push(stream.read_u8().await.unwrap());
+            }
+            let response = format!("HTTP/1.1 {}\r\n\r\n", status);
+            stream.write_all(response.as_bytes()).await.unwrap();
+            if status.starts_with("200") {
+                let mut buf = [0; 4];
+                stream.read_exact(&mut buf).await.unwrap();
+                stream.write_all(&buf).await.unwrap();
+            }
+            String::from_utf8(request).unwrap()
+        });
+        (port, task)
+    }
+
+    /// 最简单的无认证 SOCKS5 代理: 记录目标地址并回显隧道中的数据
+    async fn socks5_proxy() -> (u16, tokio::task::JoinHandle<(String, u16)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let task = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut header = [0; 2];
+            stream.read_exact(&mut header).await.unwrap();
+            let mut methods = vec![0; header[1] as usize];
+            stream.read_exact(&mut methods).await.unwrap();
+            assert!(methods.contains(&0));
+            stream.write_all(&[5, 0]).await.unwrap();
+
+            let mut request = [0; 4];
+            stream.read_exact(&mut request).await.unwrap();
+            assert_eq!(&request[..3], &[5, 1, 0]);
+            let host = match request[3] {
+                3 => {
+                    let mut name = vec![0; stream.read_u8().await.unwrap() as usize];
+                    stream.read_exact(&mut name).await.unwrap();
+                    String::from_utf8(name).unwrap()
+                }
+                4 => {
+                    let mut addr = [0; 16];
+                    stream.read_exact(&mut addr).await.unwrap();
+                    Ipv6Addr::from(addr).to_string()
+                }
+                atyp => panic!("unexpected address type {}", atyp),
+            };
+            let port = stream.read_u16().await.unwrap();
+            stream
+                .write_all(&[5, 0, 0, 1, 0, 0, 0, 0, 0, 0])
+                .await
+                .unwrap();
+
+            let mut buf = [0; 4];
+            stream.read_exact(&mut buf).await.unwrap();
+            stream.write_all(&buf).await.unwrap();
+            (host, port)
+        });
+        (port, task)
+    }
+
+    fn proxy(kind: ProxyKind, port: u16, auth: Option<ProxyAuth>) -> ProxyConfig {
+        ProxyConfig {
+            kind,
+            host: "127.0.0.1".to_string(),
+            port,
+            auth,
+        }
+    }
+
+    async fn assert_echo(stream: &mut TcpStream) {
+        stream.write_all(b"ping").await.unwrap();
+        let mut buf = [0; 4];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+    }
+
+    #[test]
+    fn authority_brackets_ipv6() {
+        assert_eq!(authority("::1", 8080), "[::1]:8080");
+        assert_eq!(authority("127.0.0.1", 8080), "127.0.0.1:8080");
+        assert_eq!(authority("localhost", 8080), "localhost:8080");
+    }
+
+    #[test]
+    fn proxy_url_with_ipv6_host_and_auth() {
+        let config = ProxyConfig {
+            kind: ProxyKind::Socks5,
+            host: "::1".to_string(),
+            port: 1080,
+            auth: Some(ProxyAuth {
+                username: "user".to_string(),
+                password: "p@ss".to_string(),
+            }),
+        };
+        assert_eq!(
+            config.url().unwrap().as_str(),
+            "socks5h://user:p%40ss@[::1]:1080"
+        );
+    }
+
+    #[tokio::test]
+    async fn http_connect_tunnel() {
+        let (port, proxy_task) = connect_proxy("200 Connection established").await;
+        let auth = ProxyAuth {
+            username: "user".to_string(),
+            password: "pass".to_string(),
+        };
+        let mut stream = proxy(ProxyKind::Http, port, Some(auth))
+            .connect("::1", 8080)
+            .await
+            .unwrap();
+        assert_echo(&mut stream).await;
+
+        let request = proxy_task.await.unwrap();
+        assert!(request.starts_with("CONNECT [::1]:8080 HTTP/1.1\r\n"));
+        assert!(request.contains("Host: [::1]:8080\r\n"));
+        assert!(request.contains(&format!(
+            "Proxy-Authorization: Basic {}\r\n",
+            STANDARD.encode("user:pass")
+        )));
+    }
+
+    #[tokio::test]
+    async fn http_connect_rejected() {
+        let (port, _proxy_task) = connect_proxy("407 Proxy Authentication Required").await;
+        let err = proxy(ProxyKind::Http, port, None)
+            .connect("example.com", 443)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("407"));
+    }
+
+    #[tokio::test]
+    async fn socks5_tunnel_resolves_on_proxy() {
+        let (port, proxy_task) = socks5_proxy().await;
+        let mut stream = proxy(ProxyKind::Socks5, port, None)
+            .connect("example.com", 443)
+            .await
+            .unwrap();
+        assert_echo(&mut stream).await;
+        assert_eq!(proxy_task.await.unwrap(), ("example.com".to_string(), 443));
+
+        let (port, proxy_task) = socks5_proxy().await;
+        let mut stream = proxy(ProxyKind::Socks5, port, None)
+            .connect("::1", 8080)
+            .await
+            .unwrap();
+        assert_echo(&mut stream).await;
+        assert_eq!(proxy_task.await.unwrap(), ("::1".to_string(), 8080));
+    }
+}
@@ -0,0 +1,171 @@
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::{ClientConfig, RootCertStore, ServerConfig};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// 客户端 TLS 配置, 设置后正向 ws 使用 `wss://`, http 使用 `https://`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct TlsConfig {
+    /// 除内置的 webpki 根证书外额外信任的根证书, PEM 文件路径.
+    /// 直接信任服务端的自签名证书时, 该证书须为 `CA:FALSE` 的终端证书并包含匹配的 SAN,
+    /// 否则会以 `CaUsedAsEndEntity` 被拒绝; 也可以改为信任签发服务端证书的 CA
+    pub root_certificates: Vec<PathBuf>,
+    /// 用于双向认证的客户端证书
+    pub client_certificate: Option<ClientCertificate>,
+}
+
+/// 客户端证书, 均为 PEM 文件路径
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ClientCertificate {
+    /// 证书链
+    pub cert_chain: PathBuf,
+    /// 私钥
+    pub private_key: PathBuf,
+}
+
+impl TlsConfig {
+    /// 构建 rustls 客户端配置, 正向 ws 与 http 共用
+    pub(crate) fn client_config(&self) -> Result<ClientConfig, anyhow::Error> {
+        let mut roots = RootCertStore::empty();
+        roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        for path in &self.root_certificates {
+            for cert in load_certs(path)? {
+                roots.add(cert)?;
+            }
+        }
+
+        let builder =
+            ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+                .with_safe_default_protocol_versions()?
+                .with_root_certificates(roots);
+        let config = match &self.client_certificate {
+            Some(cert) => builder.with_client_auth_cert(
+                load_certs(&cert.cert_chain)?,
+                load_private_key(&cert.private_key)?,
+            )?,
+            None => builder.with_no_client_auth(),
+        };
+        Ok(config)
+    }
+}
+
+/// 服务端 TLS 配置, 设置后反向 ws 服务端接受 `wss://` 连接, 均为 PEM 文件路径
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ServerTlsConfig {
+    /// 证书链
+    pub cert_chain: PathBuf,
+    /// 私钥
+    pub private_key: PathBuf,
+}
+
+impl ServerTlsConfig {
+    pub(crate) fn server_config(&self) -> Result<ServerConfig, anyhow::Error> {
+        let config =
+            ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+                .with_safe_default_protocol_versions()?
+                .with_no_client_auth()
+                .with_single_cert(
+                    load_certs(&self.cert_chain)?,
+                    load_private_key(&self.private_key)?,
+                )?;
+        Ok(config)
+    }
+}
+
+pub(crate) fn load_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>, anyhow::Error> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let certs = rustls_pemfile::certs(&mut reader).collect::<Result<Vec<_>, _>>()?;
+    if certs.is_empty() {
+        return Err(anyhow::anyhow!(
+            "No certificate found in {}",
+            path.display()
+        ));
+    }
+    Ok(certs)
+}
+
+pub(crate) fn load_private_key(path: &Path) -> Result<PrivateKeyDer<'static>, anyhow::Error> {
+    let mut reader = BufReader::new(File::open(path)?);
+    rustls_pemfile::private_key(&mut reader)?.ok_or(anyhow::anyhow!(
+        "No private key found in {}",
+        path.display()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connect::ws::{WsConfig, WsConnect};
+    use crate::connect::ReconnectPolicy;
+    use futures_util::StreamExt as _;
+    use tokio::net::TcpListener;
+    use tokio_rustls::TlsAcceptor;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(name)
+    }
+
+    /// 使用自签名证书的 wss 服务端, 返回监听端口
+    async fn wss_server() -> u16 {
+        let server = ServerTlsConfig {
+            cert_chain: fixture("localhost.crt"),
+            private_key: fixture("localhost.key"),
+        };
+        let acceptor = TlsAcceptor::from(Arc::new(server.server_config().unwrap()));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    let Ok(stream) = acceptor.accept(stream).await else {
+                        return;
+                    };
+                    let Ok(mut ws) = tokio_tungstenite::accept_async(stream).await else {
+                        return;
+                    };
+                    while let Some(Ok(_)) = ws.next().await {}
+                });
+            }
+        });
+        port
+    }
+
+    fn ws_config(port: u16, tls: TlsConfig) -> WsConfig {
+        WsConfig {
+            host: "localhost".to_string(),
+            port,
+            tls: Some(tls),
+            reconnect: ReconnectPolicy {
+                max_attempts: Some(1),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn ws_connects_with_self_signed_root() {
+        let port = wss_server().await;
+        let tls = TlsConfig {
+            root_certificates: vec![fixture("localhost.crt")],
+            client_certificate: None,
+        };
+        let connect = WsConnect::new(ws_config(port, tls)).await.unwrap();
+        assert!(connect.is_connected());
+        connect.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn ws_rejects_untrusted_certificate() {
+        let port = wss_server().await;
+        assert!(WsConnect::new(ws_config(port, TlsConfig::default()))
+            .await
+            .is_err());
+    }
+}
@@ -1,6 +1,9 @@
+use async_trait::async_trait;
 use reqwest::header::AUTHORIZATION;
+use reqwest::Url;
 use std::sync::Arc;
-use tokio::sync::{broadcast, Mutex};
+use tokio::sync::{watch, Mutex};
+use tokio::task::JoinHandle;
 use tokio_tungstenite::tungstenite::client::IntoClientRequest as _;
 use tracing::{info, warn};
 
@@ -9,24 +12,54 @@ use futures_util::{SinkExt as _, StreamExt as _};
 use serde::{Deserialize, Serialize};
 use tokio::net::TcpStream;
 use tokio_tungstenite::tungstenite::protocol::Message;
-use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+use tokio_tungstenite::{
+    client_async_tls_with_config, connect_async_tls_with_config, Connector, MaybeTlsStream,
+    WebSocketStream,
+};
 
-use crate::api::payload::ApiPayload;
-use crate::api::resp::{ApiResp, ApiRespBuilder};
+use crate::api::error::ApiError;
+use crate::api::payload::{ApiPayload, HandleQuickOperation};
+use crate::api::resp::{ApiResp, ApiRespBuilder, GetStatusResponse};
+use crate::event::meta::Meta;
+use crate::event::QuickOperation;
+use crate::traits::OneBotClient;
 use crate::Event;
 use std::time::Duration;
-use tokio::time::{sleep, timeout};
+use tokio::time::{interval_at, sleep, timeout, Instant};
 
-use super::{get_resp_builder, WsApiPayload, WsType};
+use super::channel::{EventChannel, EventChannelConfig, EventReceiver};
+use super::proxy::{authority, ProxyConfig};
+use super::tls::TlsConfig;
+use super::{
+    next_ping, shutdown_signal, CallOptions, ConnectionState, HeartbeatConfig, HeartbeatWatchdog,
+    PendingRequests, ReconnectPolicy, TokenPlacement, WsApiPayload, WsFrame, WsType,
+};
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct WsConfig {
     pub host: String,
     pub port: u16,
     pub r#type: WsType,
+    /// 机器人 QQ 号, 收到事件前用于 `ConnectionState::Connected`
     pub bot_id: Option<String>,
     pub bot_nick_name: Option<String>,
     pub access_token: Option<String>,
+    /// access token 的传递方式
+    pub token_placement: TokenPlacement,
+    /// 设置后使用 `wss://` 连接
+    pub tls: Option<TlsConfig>,
+    /// 设置后通过代理连接
+    pub proxy: Option<ProxyConfig>,
+    /// 连接及断线重连策略, `WsConnect::new` 在策略耗尽时返回错误
+    pub reconnect: ReconnectPolicy,
+    /// 等待 API 响应的默认超时时间, 可通过 `CallOptions` 单独设置
+    pub api_timeout: Duration,
+    /// 心跳看门狗, 设置后错过指定次数的心跳会断开并重连
+    pub heartbeat: Option<HeartbeatConfig>,
+    /// 设置后按该间隔向 OneBot 实现发送 Ping
+    pub ping_interval: Option<Duration>,
+    /// 事件通道容量与模式
+    pub events: EventChannelConfig,
 }
 
 impl Default for WsConfig {
@@ -36,8 +69,16 @@ impl Default for WsConfig {
             r#type: WsType::Universal,
             port: 8081,
             access_token: None,
+            token_placement: TokenPlacement::default(),
             bot_id: None,
             bot_nick_name: None,
+            tls: None,
+            proxy: None,
+            reconnect: ReconnectPolicy::default(),
+            api_timeout: Duration::from_secs(30),
+            heartbeat: None,
+            ping_interval: None,
+            events: EventChannelConfig::default(),
         }
     }
 }
@@ -46,139 +87,715 @@ pub struct WsConnect {
     pub config: WsConfig,
     ws_read: Mutex<SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>>,
     ws_write: Mutex<SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>>,
-    event_sender: broadcast::Sender<Event>,
-    api_response_sender: broadcast::Sender<ApiRespBuilder>,
+    events: EventChannel,
+    pending: PendingRequests,
+    state_sender: watch::Sender<ConnectionState>,
+    last_status: std::sync::Mutex<Option<GetStatusResponse>>,
+    disconnect_reason: std::sync::Mutex<Option<String>>,
+    shutdown: watch::Sender<bool>,
+    task: std::sync::Mutex<Option<JoinHandle<()>>>,
 }
 
 impl WsConnect {
     pub async fn new(ws_config: WsConfig) -> Result<Arc<Self>, anyhow::Error> {
-        let (ws_write, ws_read) = Self::connect(&ws_config).await;
-        let (api_response_sender, _) = broadcast::channel(100);
+        let (state_sender, _) = watch::channel(ConnectionState::Connecting);
+        let (ws_write, ws_read) = Self::connect(&ws_config, &state_sender, false).await?;
         let self_ = Arc::new(Self {
             config: ws_config.clone(),
             ws_read: Mutex::new(ws_read),
             ws_write: Mutex::new(ws_write),
-            event_sender: broadcast::channel(100).0,
-            api_response_sender,
+            events: EventChannel::new(&ws_config.events),
+            pending: PendingRequests::default(),
+            state_sender,
+            last_status: std::sync::Mutex::new(None),
+            disconnect_reason: std::sync::Mutex::new(None),
+            shutdown: watch::channel(false).0,
+            task: std::sync::Mutex::new(None),
         });
 
-        self_.clone().start_event_listener();
+        let task = tokio::spawn(self_.clone().run());
+        *self_.task.lock().unwrap() = Some(task);
         Ok(self_)
     }
 
+    /// 按重连策略建立连接, 过程中更新连接状态, `reconnect` 表示是否为断线重连
     async fn connect(
         config: &WsConfig,
-    ) -> (
-        SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>,
-        SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>,
-    ) {
-        let url = format!(
-            "ws://{}:{}{}",
-            config.host,
-            config.port,
+        state: &watch::Sender<ConnectionState>,
+        reconnect: bool,
+    ) -> Result<
+        (
+            SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>,
+            SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>,
+        ),
+        anyhow::Error,
+    > {
+        let connector = match &config.tls {
+            Some(tls) => Some(Connector::Rustls(Arc::new(tls.client_config()?))),
+            None => None,
+        };
+        let mut url = Url::parse(&format!(
+            "{}://{}{}",
+            if config.tls.is_some() { "wss" } else { "ws" },
+            authority(&config.host, config.port),
             match config.r#type {
                 WsType::Event => "/event",
                 WsType::Api => "/api",
                 WsType::Universal => "",
             }
-        );
+        ))?;
+        if let Some(token) = &config.access_token {
+            if config.token_placement.query() {
+                url.query_pairs_mut().append_pair("access_token", token);
+            }
+        }
+        let mut attempt = 0;
         loop {
             let url = url.clone();
+            state.send_replace(if reconnect {
+                ConnectionState::Reconnecting(attempt + 1)
+            } else {
+                ConnectionState::Connecting
+            });
 
             // 创建带有 Authorization 头的请求
-            let mut request = url.into_client_request().unwrap();
-            if let Some(token) = config.access_token.clone() {
+            let mut request = url.as_str().into_client_request()?;
+            if let Some(token) = config
+                .access_token
+                .clone()
+                .filter(|_| config.token_placement.header())
+            {
                 request
                     .headers_mut()
-                    .insert(AUTHORIZATION, format!("Bearer {}", token).parse().unwrap());
+                    .insert(AUTHORIZATION, format!("Bearer {}", token).parse()?);
             }
-            match connect_async(request).await {
+            let result = match &config.proxy {
+                Some(proxy) => match proxy.connect(&config.host, config.port).await {
+                    Ok(stream) => {
+                        client_async_tls_with_config(request, stream, None, connector.clone())
+                            .await
+                            .map_err(anyhow::Error::from)
+                    }
+                    Err(e) => Err(e),
+                },
+                None => connect_async_tls_with_config(request, None, false, connector.clone())
+                    .await
+                    .map_err(anyhow::Error::from),
+            };
+            match result {
                 Ok((ws_stream, _)) => {
                     let (write, read) = ws_stream.split();
                     info!("Connection succeed");
-                    break (write, read);
+                    state.send_replace(ConnectionState::Connected(
+                        config.bot_id.clone(),
+                        config.r#type.clone(),
+                    ));
+                    break Ok((write, read));
                 }
                 Err(e) => {
+                    attempt += 1;
+                    if !config.reconnect.should_retry(attempt) {
+                        let e = anyhow::anyhow!(
+                            "Connection failed after {} attempts, WebSocket server: {}",
+                            attempt,
+                            e
+                        );
+                        state.send_replace(ConnectionState::Disconnected(e.to_string()));
+                        break Err(e);
+                    }
+                    let delay = config.reconnect.delay(attempt);
                     warn!(
-                        "Connection failed, WebSocket server: {}, will retry in 3 seconds",
-                        e
+                        "Connection failed, WebSocket server: {}, will retry in {:?} (attempt {})",
+                        e, delay, attempt
                     );
+                    sleep(delay).await;
                 }
             }
-            sleep(Duration::from_secs(3)).await;
         }
     }
 
-    fn start_event_listener(self: Arc<Self>) {
-        let self_clone = Arc::clone(&self);
+    /// 接收消息并在断线后重连, 直到调用 `shutdown()` 或重连策略耗尽
+    async fn run(self: Arc<Self>) {
+        let mut shutdown = self.shutdown.subscribe();
+        loop {
+            let reason = self.listen(&mut shutdown).await;
+            self.pending.fail_all(&reason);
+            *self.disconnect_reason.lock().unwrap() = Some(reason.clone());
+            if *self.shutdown.borrow() {
+                self.state_sender.send_replace(ConnectionState::Closed);
+                info!("Connection shut down");
+                break;
+            }
+            self.state_sender
+                .send_replace(ConnectionState::Disconnected(reason));
+            let result = tokio::select! {
+                result = Self::connect(&self.config, &self.state_sender, true) => result,
+                _ = shutdown_signal(&mut shutdown) => {
+                    self.state_sender.send_replace(ConnectionState::Closed);
+                    info!("Connection shut down while reconnecting");
+                    break;
+                }
+            };
+            match result {
+                Ok((ws_write, ws_read)) => {
+                    *self.ws_write.lock().await = ws_write;
+                    *self.ws_read.lock().await = ws_read;
+                    info!("Reconnected to WebSocket server",)
+                }
+                Err(e) => {
+                    warn!("Reconnect failed, giving up: {}", e);
+                    break;
+                }
+            }
+        }
+    }
 
-        tokio::spawn(async move {
-            {
-                let mut read = self.ws_read.lock().await;
-
-                while let Some(msg) = read.next().await {
-                    match msg {
-                        Ok(msg) => {
-                            let msg_string = msg.to_string();
-                            match serde_json::from_str::<Event>(&msg_string) {
-                                Ok(event) => match event {
-                                    Event::ApiRespBuilder(api_resp_builder) => {
-                                        if let Err(e) =
-                                            self.api_response_sender.send(api_resp_builder)
-                                        {
-                                            warn!("Error sending ApiRespBuilder: {}", e);
-                                        }
-                                    }
-                                    other => {
-                                        if let Err(e) = self.event_sender.send(other) {
-                                            warn!("Error sending Event: {}", e);
-                                        }
-                                    }
-                                },
-                                Err(e) => {
-                                    warn!("Error parsing Event: {}, Raw: {}", e, msg_string);
+    /// 处理当前连接上的消息直到连接断开, 返回断开原因
+    async fn listen(&self, shutdown: &mut watch::Receiver<bool>) -> String {
+        let mut reason = "WsMessage stream ended".to_string();
+        let mut watchdog = HeartbeatWatchdog::new(self.config.heartbeat.clone());
+        let mut close = false;
+        let mut ping = self
+            .config
+            .ping_interval
+            .map(|period| interval_at(Instant::now() + period, period));
+        {
+            let mut read = self.ws_read.lock().await;
+
+            loop {
+                let msg = tokio::select! {
+                    msg = read.next() => msg,
+                    timeout = watchdog.expired() => {
+                        warn!("No heartbeat received in {:?}, closing connection", timeout);
+                        reason = format!("Heartbeat timeout after {:?}", timeout);
+                        close = true;
+                        break;
+                    }
+                    _ = shutdown_signal(shutdown) => {
+                        reason = "Connection shut down".to_string();
+                        close = true;
+                        break;
+                    }
+                    _ = next_ping(&mut ping) => {
+                        let send_ping = async {
+                            let mut write = self.ws_write.lock().await;
+                            write.send(Message::Ping(Vec::new())).await
+                        };
+                        // 对方不读取时发送可能一直阻塞, 同样需要响应关闭
+                        tokio::select! {
+                            result = send_ping => {
+                                if let Err(e) = result {
+                                    warn!("Error sending Ping: {}", e);
+                                }
+                            }
+                            _ = shutdown_signal(shutdown) => {
+                                reason = "Connection shut down".to_string();
+                                close = true;
+                                break;
+                            }
+                        }
+                        continue;
+                    }
+                };
+                let Some(msg) = msg else {
+                    break;
+                };
+                match msg {
+                    Ok(msg) => match WsFrame::from(msg) {
+                        WsFrame::Event(Event::ApiRespBuilder(api_resp_builder)) => {
+                            let echo = api_resp_builder.echo.clone();
+                            if !self.pending.resolve(api_resp_builder) {
+                                warn!(
+                                    "Received ApiRespBuilder without pending call, echo: {}",
+                                    echo
+                                );
+                            }
+                        }
+                        WsFrame::Event(event) => {
+                            if let Some(self_id) = event.self_id() {
+                                self.update_self_id(self_id.to_string());
+                            }
+                            if let Event::Meta(Meta::Heartbeat(heartbeat)) = &event {
+                                if let Some(status) = watchdog.beat(heartbeat) {
+                                    *self.last_status.lock().unwrap() = Some(status);
                                 }
                             }
+                            // 事件与 API 响应共用连接, 队列已满时断开而不是暂停读取
+                            if !self.events.try_send(event) {
+                                reason = "Event queue is full".to_string();
+                                close = true;
+                                break;
+                            }
                         }
-                        Err(e) => {
-                            warn!("Error receiving WsMessage: {}", e);
+                        WsFrame::Close(close_reason) => {
+                            warn!("{}", close_reason);
+                            reason = close_reason;
                         }
+                        WsFrame::Skip => {}
+                    },
+                    Err(e) => {
+                        warn!("Error receiving WsMessage: {}", e);
+                        reason = e.to_string();
                     }
                 }
-                warn!("WsMessage stream ended, attempting to reconnect");
             }
-            let (ws_write, ws_read) = Self::connect(&self_clone.config).await;
-            *self_clone.ws_write.lock().await = ws_write;
-            *self_clone.ws_read.lock().await = ws_read;
-            self_clone.start_event_listener();
-            info!("Reconnected to WebSocket server",)
+        }
+        if close {
+            // 正在发送的调用可能持有写锁, 等待写锁同样计入超时
+            let close = async { self.ws_write.lock().await.close().await };
+            let _ = timeout(Duration::from_secs(1), close).await;
+        } else {
+            warn!("WsMessage stream ended, attempting to reconnect");
+        }
+        reason
+    }
+
+    /// 以事件中的 `self_id` 更新连接状态中的机器人 QQ 号
+    fn update_self_id(&self, self_id: String) {
+        self.state_sender.send_if_modified(|state| match state {
+            ConnectionState::Connected(bot_id, _) if bot_id.as_ref() != Some(&self_id) => {
+                *bot_id = Some(self_id);
+                true
+            }
+            _ => false,
         });
     }
 
-    pub async fn subscribe(&self) -> broadcast::Receiver<Event> {
-        self.event_sender.subscribe()
+    /// 关闭连接: 发送关闭帧, 停止重连, 使等待中的调用失败并等待后台任务退出
+    pub async fn shutdown(&self) {
+        self.shutdown.send_replace(true);
+        let task = self.task.lock().unwrap().take();
+        if let Some(task) = task {
+            if let Err(e) = task.await {
+                warn!("Error joining WebSocket task: {}", e);
+            }
+        }
+        self.state_sender.send_replace(ConnectionState::Closed);
+    }
+
+    pub async fn subscribe(&self) -> EventReceiver {
+        self.events.subscribe()
+    }
+
+    /// 订阅者因处理过慢而丢失的事件总数
+    pub fn dropped_events(&self) -> u64 {
+        self.events.dropped_events()
+    }
+
+    /// 观察连接状态
+    pub fn state(&self) -> watch::Receiver<ConnectionState> {
+        self.state_sender.subscribe()
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.state_sender.borrow().is_connected()
+    }
+
+    /// 最近一次断开连接的原因, 对方发送关闭帧时包含关闭码与原因
+    pub fn disconnect_reason(&self) -> Option<String> {
+        self.disconnect_reason.lock().unwrap().clone()
+    }
+
+    /// 最近一次心跳事件中的运行状态
+    pub fn last_status(&self) -> Option<GetStatusResponse> {
+        self.last_status.lock().unwrap().clone()
     }
 
-    pub async fn call_api(self: Arc<Self>, api_data: ApiPayload) -> Result<ApiResp, anyhow::Error> {
+    pub async fn call_api(&self, api_data: ApiPayload) -> Result<ApiResp, ApiError> {
+        self.call_api_with(api_data, CallOptions::default()).await
+    }
+
+    /// 使用指定选项调用 API, 调用方取消（丢弃 future）时会清理等待中的请求
+    pub async fn call_api_with(
+        &self,
+        api_data: ApiPayload,
+        options: CallOptions,
+    ) -> Result<ApiResp, ApiError> {
         let resp_type = api_data.to_resp_type();
-        let ws_api_data: WsApiPayload = api_data.into();
+        self.call_api_raw(api_data, options).await?.build(resp_type)
+    }
+
+    /// 调用 API 并返回未解析 `data` 的响应
+    pub async fn call_api_raw(
+        &self,
+        api_data: ApiPayload,
+        options: CallOptions,
+    ) -> Result<ApiRespBuilder, ApiError> {
+        if !self.is_connected() {
+            return Err(ApiError::NotSent(anyhow::anyhow!(
+                "[WsConnect.call_api] Not connected, state: {:?}",
+                *self.state_sender.borrow()
+            )));
+        }
+        let ws_api_data = WsApiPayload::new(api_data, options.mode);
         let echo = ws_api_data.echo.clone();
-        let ws_api_string: String = serde_json::to_string(&ws_api_data)?;
-        {
+        let ws_api_string: String =
+            serde_json::to_string(&ws_api_data).map_err(|e| ApiError::Transport(e.into()))?;
+        let pending_call = self.pending.register(echo);
+        let send = async {
             let mut write = self.ws_write.lock().await;
-            write.send(Message::Text(ws_api_string)).await?;
+            Ok(write.send(Message::Text(ws_api_string)).await?)
+        };
+        pending_call
+            .send_and_wait(options.timeout.unwrap_or(self.config.api_timeout), send)
+            .await
+    }
+
+    /// 对收到的事件执行快速操作, `context` 为原始事件
+    pub async fn handle_quick_operation(
+        &self,
+        context: Event,
+        operation: QuickOperation,
+    ) -> Result<ApiResp, ApiError> {
+        self.call_api(ApiPayload::HandleQuickOperation(HandleQuickOperation {
+            context,
+            operation,
+        }))
+        .await
+    }
+}
+
+#[async_trait]
+impl OneBotClient for WsConnect {
+    async fn call_api_raw(
+        &self,
+        api_data: ApiPayload,
+        options: CallOptions,
+    ) -> Result<ApiRespBuilder, ApiError> {
+        WsConnect::call_api_raw(self, api_data, options).await
+    }
+
+    async fn subscribe(&self) -> Option<EventReceiver> {
+        Some(WsConnect::subscribe(self).await)
+    }
+
+    fn is_connected(&self) -> bool {
+        WsConnect::is_connected(self)
+    }
+
+    async fn shutdown(&self) {
+        WsConnect::shutdown(self).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::payload::{GetStatus, SetGroupCard};
+    use crate::connect::channel::EventChannelMode;
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+
+    /// 模拟 OneBot 实现: 连接后上报 `events` 个心跳事件, 之后响应所有 API 调用
+    async fn onebot_server(events: usize) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            let heartbeat = serde_json::json!({
+                "time": 0,
+                "self_id": 10001,
+                "post_type": "meta_event",
+                "meta_event_type": "heartbeat",
+                "status": {"online": true, "good": true},
+                "interval": 5000,
+            });
+            for _ in 0..events {
+                if ws.send(Message::Text(heartbeat.to_string())).await.is_err() {
+                    return;
+                }
+            }
+            while let Some(Ok(msg)) = ws.next().await {
+                let Message::Text(text) = msg else {
+                    continue;
+                };
+                let request: serde_json::Value = serde_json::from_str(&text).unwrap();
+                let response = serde_json::json!({
+                    "status": "ok",
+                    "retcode": 0,
+                    "data": null,
+                    "echo": request["echo"],
+                });
+                if ws.send(Message::Text(response.to_string())).await.is_err() {
+                    return;
+                }
+            }
+        });
+        port
+    }
+
+    /// 完成握手后不再读取的对端
+    async fn silent_server() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let _ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            std::future::pending::<()>().await;
+        });
+        port
+    }
+
+    /// 上报一次心跳后保持连接但不再发送任何数据, 且不接受重连
+    async fn stalled_server() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            drop(listener);
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            let heartbeat = serde_json::json!({
+                "time": 0,
+                "self_id": 10001,
+                "post_type": "meta_event",
+                "meta_event_type": "heartbeat",
+                "status": {"online": true, "good": false},
+                "interval": 1000,
+            });
+            ws.send(Message::Text(heartbeat.to_string())).await.unwrap();
+            std::future::pending::<()>().await;
+        });
+        port
+    }
+
+    /// 握手后立即以 `code` 和 `reason` 关闭连接, 且不接受重连
+    async fn closing_server(code: u16, reason: &'static str) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            drop(listener);
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            ws.close(Some(CloseFrame {
+                code: code.into(),
+                reason: reason.into(),
+            }))
+            .await
+            .unwrap();
+            while ws.next().await.is_some() {}
+        });
+        port
+    }
+
+    fn lossless_config(port: u16) -> WsConfig {
+        WsConfig {
+            port,
+            reconnect: ReconnectPolicy {
+                max_attempts: Some(1),
+                ..Default::default()
+            },
+            events: EventChannelConfig {
+                capacity: 4,
+                mode: EventChannelMode::Lossless,
+            },
+            ..Default::default()
         }
-        let subscriber = self.api_response_sender.subscribe();
-        let resp_builder = timeout(
-            Duration::from_secs(30),
-            get_resp_builder(subscriber, echo)
+    }
+
+    #[tokio::test]
+    async fn connected_state_reports_self_id_from_events() {
+        let connect = WsConnect::new(lossless_config(onebot_server(1).await))
+            .await
+            .unwrap();
+        timeout(
+            Duration::from_secs(5),
+            connect.state().wait_for(|state| {
+                *state == ConnectionState::Connected(Some("10001".to_string()), WsType::Universal)
+            }),
         )
         .await
-        .ok()
-        .flatten()
-        .ok_or(anyhow::anyhow!(
-            "[WsConnect.call_api] Error receiving API response, maybe the API response channel is closed or timeout"
-        ))?;
-        Ok(resp_builder.build(resp_type)?)
+        .unwrap()
+        .unwrap();
+        connect.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn disconnect_reason_reports_close_frame() {
+        let connect = WsConnect::new(WsConfig {
+            port: closing_server(4000, "bot offline").await,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        timeout(
+            Duration::from_secs(5),
+            connect
+                .state()
+                .wait_for(|state| matches!(state, ConnectionState::Reconnecting(_))),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            connect.disconnect_reason().unwrap(),
+            "Closed by peer, code: 4000, reason: bot offline"
+        );
+        connect.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn pings_follow_configured_interval() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (pings_tx, mut pings) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            while let Some(Ok(msg)) = ws.next().await {
+                if let Message::Ping(_) = msg {
+                    let _ = pings_tx.send(Instant::now());
+                }
+            }
+        });
+        let start = Instant::now();
+        let connect = WsConnect::new(WsConfig {
+            port,
+            ping_interval: Some(Duration::from_millis(100)),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        let mut last = start;
+        for _ in 0..3 {
+            let ping = timeout(Duration::from_secs(5), pings.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            let gap = ping - last;
+            assert!(gap >= Duration::from_millis(90), "ping after {:?}", gap);
+            assert!(gap < Duration::from_millis(500), "ping after {:?}", gap);
+            last = ping;
+        }
+        connect.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn heartbeat_timeout_triggers_reconnect() {
+        let connect = WsConnect::new(WsConfig {
+            port: stalled_server().await,
+            heartbeat: Some(HeartbeatConfig { missed_beats: 2 }),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        timeout(Duration::from_secs(5), async {
+            while connect.last_status().is_none() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(
+            connect.last_status(),
+            Some(GetStatusResponse {
+                online: Some(true),
+                good: false
+            })
+        );
+
+        // 之后不再收到心跳, 暂停时钟并让其自动推进到看门狗超时
+        tokio::time::pause();
+        let start = Instant::now();
+        connect
+            .state()
+            .wait_for(|state| matches!(state, ConnectionState::Reconnecting(_)))
+            .await
+            .unwrap();
+        assert!(start.elapsed() <= Duration::from_secs(2));
+        assert!(connect
+            .disconnect_reason()
+            .unwrap()
+            .contains("Heartbeat timeout after 2s"));
+        connect.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn call_timeout_covers_blocked_send() {
+        let connect = WsConnect::new(lossless_config(silent_server().await))
+            .await
+            .unwrap();
+        let options = CallOptions {
+            timeout: Some(Duration::from_millis(500)),
+            ..Default::default()
+        };
+        // 足以填满套接字缓冲区的请求, 对端不读取时发送会一直阻塞
+        let large = ApiPayload::SetGroupCard(SetGroupCard {
+            group_id: 1,
+            user_id: 1,
+            card: "x".repeat(32 * 1024 * 1024),
+        });
+        for api_data in [large, ApiPayload::GetStatus(GetStatus {})] {
+            let start = Instant::now();
+            let result = timeout(
+                Duration::from_secs(5),
+                connect.call_api_raw(api_data, options.clone()),
+            )
+            .await
+            .expect("call should time out on its own");
+            assert!(matches!(result, Err(ApiError::Timeout(_))));
+            assert!(start.elapsed() < Duration::from_secs(3));
+        }
+        connect.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn shutdown_with_unread_lossless_events() {
+        let connect = WsConnect::new(lossless_config(onebot_server(1000).await))
+            .await
+            .unwrap();
+        let _subscriber = connect.subscribe().await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        timeout(Duration::from_secs(5), connect.shutdown())
+            .await
+            .expect("shutdown should not wait for the subscriber");
+        assert_eq!(*connect.state().borrow(), ConnectionState::Closed);
+    }
+
+    #[tokio::test]
+    async fn lossless_backlog_does_not_block_api_responses() {
+        // 队列恰好填满且无人读取
+        let connect = WsConnect::new(lossless_config(onebot_server(4).await))
+            .await
+            .unwrap();
+        let _subscriber = connect.subscribe().await;
+        let options = CallOptions {
+            timeout: Some(Duration::from_secs(5)),
+            ..Default::default()
+        };
+        let resp = connect
+            .call_api_raw(ApiPayload::GetStatus(GetStatus {}), options)
+            .await
+            .unwrap();
+        assert_eq!(resp.status, "ok");
+        connect.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn lossless_overflow_disconnects() {
+        let connect = WsConnect::new(lossless_config(onebot_server(8).await))
+            .await
+            .unwrap();
+        let mut subscriber = connect.subscribe().await;
+        timeout(
+            Duration::from_secs(5),
+            connect
+                .state()
+                .wait_for(|state| !state.is_connected() && *state != ConnectionState::Connecting),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            connect.disconnect_reason().as_deref(),
+            Some("Event queue is full")
+        );
+        assert_eq!(connect.dropped_events(), 1);
+        // 断开前已入队的事件不会丢失
+        for _ in 0..4 {
+            assert!(subscriber.recv().await.is_some());
+        }
+        connect.shutdown().await;
     }
 }
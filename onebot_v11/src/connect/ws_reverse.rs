@@ -1,27 +1,65 @@
-use crate::api::payload::ApiPayload;
-use crate::api::resp::{ApiResp, ApiRespBuilder};
+use crate::api::error::ApiError;
+use crate::api::payload::{ApiPayload, HandleQuickOperation};
+use crate::api::resp::{ApiResp, ApiRespBuilder, GetStatusResponse};
+use crate::event::meta::Meta;
+use crate::event::QuickOperation;
+use crate::traits::OneBotClient;
 use crate::Event;
+use async_trait::async_trait;
 use futures_util::stream::{SplitSink, SplitStream};
 use futures_util::{SinkExt as _, StreamExt as _};
 use reqwest::header::AUTHORIZATION;
+use reqwest::Url;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::{broadcast, Mutex, RwLock};
-use tokio::time::timeout;
+use tokio::sync::{watch, Mutex, Notify, RwLock};
+use tokio::task::JoinHandle;
+use tokio_rustls::TlsAcceptor;
 use tokio_tungstenite::accept_hdr_async;
-use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
 use tokio_tungstenite::tungstenite::protocol::Message;
 use tokio_tungstenite::WebSocketStream;
 use tracing::{info, warn};
 
-use super::{get_resp_builder, WsApiPayload, WsType};
+use super::auth::{redact, AccessControl, AuthRejection, AuthRequest, Authenticator};
+use super::channel::{EventChannel, EventChannelConfig, EventReceiver};
+use super::tls::ServerTlsConfig;
+use super::{
+    shutdown_signal, CallOptions, ConnectionState, HeartbeatConfig, HeartbeatWatchdog,
+    PendingRequests, TokenPlacement, WsApiPayload, WsFrame, WsType,
+};
+
+/// 反向 ws 连接的底层流, 明文 TCP 或 TLS
+trait ServerStream: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> ServerStream for T {}
+
+type ServerWsStream = WebSocketStream<Box<dyn ServerStream>>;
 
 pub struct ReverseWsConfig {
     pub host: String,
     pub port: u16,
+    /// 未设置 `routes` 时接受以此结尾的任意路径
     pub suffix: String,
+    /// 路由表, 设置后只接受表中的路径, `suffix` 不再生效
+    pub routes: Vec<ReverseWsRoute>,
     pub access_token: Option<String>,
+    /// 自定义鉴权, 设置后代替 `access_token` 的校验, 可使用 `AccessControl`
+    pub authenticator: Option<Arc<dyn Authenticator>>,
+    /// 从何处读取客户端发送的 access token
+    pub token_placement: TokenPlacement,
+    /// 设置后只接受 `wss://` 连接
+    pub tls: Option<ServerTlsConfig>,
+    /// 等待 API 响应的默认超时时间, 可通过 `CallOptions` 单独设置
+    pub api_timeout: Duration,
+    /// 心跳看门狗, 设置后错过指定次数心跳的连接会被断开
+    pub heartbeat: Option<HeartbeatConfig>,
+    /// 事件通道容量与模式
+    pub events: EventChannelConfig,
 }
 
 impl Default for ReverseWsConfig {
@@ -30,199 +68,1177 @@ impl Default for ReverseWsConfig {
             host: "127.0.0.1".to_string(),
             port: 8080,
             suffix: "onebot/v11".to_string(),
+            routes: Vec::new(),
             access_token: None,
+            authenticator: None,
+            token_placement: TokenPlacement::default(),
+            tls: None,
+            api_timeout: Duration::from_secs(30),
+            heartbeat: None,
+            events: EventChannelConfig::default(),
         }
     }
 }
 
+impl ReverseWsConfig {
+    /// 按完整路径查找路由, 忽略首尾的 `/`
+    fn route(&self, path: &str) -> Option<&ReverseWsRoute> {
+        let path = path.trim_matches('/');
+        self.routes
+            .iter()
+            .find(|route| route.path.trim_matches('/') == path)
+    }
+}
+
+/// 反向 ws 路由, 如 go-cqhttp 的 `/onebot/v11/api`, `/onebot/v11/event` 与 `/onebot/v11/ws`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ReverseWsRoute {
+    /// 完整的请求路径
+    pub path: String,
+    /// 该路径上连接的类型, 设置后忽略 `X-Client-Role` 头
+    pub role: Option<WsType>,
+    /// 设置后只接受 `X-Self-ID` 为该账号的连接
+    pub bot_id: Option<String>,
+}
+
+/// 反向 ws 服务端上的一条客户端连接
+pub struct ReverseWsClient {
+    pub id: u64,
+    /// 连接时 `X-Self-ID` 头中的机器人 QQ 号
+    pub bot_id: String,
+    /// 连接类型, 由路由或 `X-Client-Role` 头决定, 缺省为 `Universal`
+    pub r#type: WsType,
+    /// 连接时的请求路径
+    pub path: String,
+    ws_write: Mutex<SplitSink<ServerWsStream, Message>>,
+    pending: PendingRequests,
+    last_status: std::sync::Mutex<Option<GetStatusResponse>>,
+}
+
+impl ReverseWsClient {
+    /// 该连接是否可以用于调用 API
+    pub fn is_api_capable(&self) -> bool {
+        matches!(self.r#type, WsType::Api | WsType::Universal)
+    }
+
+    /// 该连接是否会上报事件
+    pub fn is_event_capable(&self) -> bool {
+        matches!(self.r#type, WsType::Event | WsType::Universal)
+    }
+
+    /// 该连接最近一次心跳事件中的运行状态
+    pub fn last_status(&self) -> Option<GetStatusResponse> {
+        self.last_status.lock().unwrap().clone()
+    }
+}
+
+/// 同一机器人的会话, 将该机器人的 Event / Api 连接配对, Universal 连接同时承担两者
+#[derive(Clone)]
+pub struct BotSession {
+    pub bot_id: String,
+    /// 上报事件的连接
+    pub event: Option<Arc<ReverseWsClient>>,
+    /// 调用 API 的连接
+    pub api: Option<Arc<ReverseWsClient>>,
+}
+
+impl BotSession {
+    fn attach(&mut self, client: &Arc<ReverseWsClient>) {
+        if client.is_event_capable() {
+            self.event = Some(client.clone());
+        }
+        if client.is_api_capable() {
+            self.api = Some(client.clone());
+        }
+    }
+
+    fn detach(&mut self, client: &ReverseWsClient) {
+        if self.event.as_ref().is_some_and(|c| c.id == client.id) {
+            self.event = None;
+        }
+        if self.api.as_ref().is_some_and(|c| c.id == client.id) {
+            self.api = None;
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.event.is_none() && self.api.is_none()
+    }
+}
+
 pub struct ReverseWsConnect {
     pub config: ReverseWsConfig,
-    pub r#type: RwLock<Option<WsType>>,
-    pub r#bot_id: RwLock<Option<String>>,
-    ws_read: Mutex<SplitStream<WebSocketStream<TcpStream>>>,
-    ws_write: Mutex<SplitSink<WebSocketStream<TcpStream>, Message>>,
-    event_sender: broadcast::Sender<Event>,
-    api_response_sender: broadcast::Sender<ApiRespBuilder>,
+    sessions: RwLock<HashMap<String, BotSession>>,
+    /// 有连接登记时通知 `wait_for_bot` / `wait_connected`
+    session_attached: Notify,
+    next_client_id: AtomicU64,
+    events: EventChannel,
+    state_sender: watch::Sender<ConnectionState>,
+    /// 每个机器人的连接状态, 机器人断开后保留以便观察重连
+    bot_states: std::sync::Mutex<HashMap<String, watch::Sender<ConnectionState>>>,
+    authenticator: Arc<dyn Authenticator>,
+    shutdown: watch::Sender<bool>,
+    task: std::sync::Mutex<Option<JoinHandle<()>>>,
 }
 
 impl ReverseWsConnect {
+    /// 绑定端口并在后台接受连接, 不等待客户端连接即返回.
+    /// 需要在调用 API 前确保已有客户端连接时, 使用 `wait_connected` 或 `wait_for_bot`
     pub async fn new(config: ReverseWsConfig) -> Result<Arc<Self>, anyhow::Error> {
-        let ((ws_read, ws_write), bot_id, r#type) = Self::connect(&config).await?;
-        let (api_response_sender, _) = broadcast::channel(100);
+        let tls_acceptor = match &config.tls {
+            Some(tls) => Some(TlsAcceptor::from(Arc::new(tls.server_config()?))),
+            None => None,
+        };
+        let listener = TcpListener::bind(format!("{}:{}", config.host, config.port)).await?;
+        info!(
+            "Reverse WebSocket server listening on {}:{}",
+            config.host, config.port
+        );
+        let events = EventChannel::new(&config.events);
+        let authenticator = config.authenticator.clone().unwrap_or_else(|| {
+            Arc::new(AccessControl {
+                access_token: config.access_token.clone(),
+                ..Default::default()
+            })
+        });
         let self_ = Arc::new(Self {
             config,
-            r#type: RwLock::new(r#type),
-            r#bot_id: RwLock::new(bot_id),
-            ws_read: Mutex::new(ws_read),
-            ws_write: Mutex::new(ws_write),
-            event_sender: broadcast::channel(100).0,
-            api_response_sender,
+            sessions: RwLock::new(HashMap::new()),
+            session_attached: Notify::new(),
+            next_client_id: AtomicU64::new(0),
+            events,
+            state_sender: watch::channel(ConnectionState::Connecting).0,
+            bot_states: std::sync::Mutex::new(HashMap::new()),
+            authenticator,
+            shutdown: watch::channel(false).0,
+            task: std::sync::Mutex::new(None),
         });
 
-        self_.clone().start_event_listener();
+        let task = tokio::spawn(self_.clone().run(listener, tls_acceptor));
+        *self_.task.lock().unwrap() = Some(task);
 
         Ok(self_)
     }
 
-    async fn connect(
-        config: &ReverseWsConfig,
-    ) -> Result<
-        (
-            (
-                SplitStream<WebSocketStream<TcpStream>>,
-                SplitSink<WebSocketStream<TcpStream>, Message>,
-            ),
-            Option<String>,
-            Option<WsType>,
-        ),
-        anyhow::Error,
-    > {
+    /// 接受连接直到调用 `shutdown()`, 之后等待所有连接关闭
+    async fn run(self: Arc<Self>, listener: TcpListener, tls_acceptor: Option<TlsAcceptor>) {
+        let mut shutdown = self.shutdown.subscribe();
+        let mut connections: Vec<JoinHandle<()>> = Vec::new();
         loop {
-            let listener = TcpListener::bind(format!("{}:{}", config.host, config.port)).await?;
-
-            let mut bot_id = None;
-            let mut r#type = None;
-            match listener.accept().await {
-                Ok((stream, _)) => {
-                    match accept_hdr_async(stream, |req: &Request, mut resp: Response| {
-                        let path = req.uri().path().trim_end_matches('/');
-                        if !path.ends_with(&config.suffix) {
-                            *resp.status_mut() = reqwest::StatusCode::NOT_FOUND;
-                            return Ok(resp);
-                        }
-                        let headers = req.headers();
-                        bot_id = headers
-                            .get("X-Self-ID")
-                            .map(|v| v.to_str().unwrap_or("").to_string());
-                        r#type = headers
-                            .get("X-Client-Role")
-                            .map(|v| WsType::from_str(&v.to_str().unwrap_or("").to_string()));
-                        let bear_token = headers
-                            .get(AUTHORIZATION)
-                            .map(|v| v.to_str().unwrap_or("").to_string());
-                        tracing::info!(
-                            "Connection accepting: bot_id: {:?}, type: {:?}, bear_token: {:?}",
-                            bot_id,
-                            r#type,
-                            bear_token
-                        );
-                        if bear_token
-                            != config
-                                .access_token
-                                .as_ref()
-                                .and_then(|s| Some(format!("Bearer {}", s)))
-                        {
-                            tracing::error!(
-                                "Connection failed: Unauthorized, bear_token: {:?}",
-                                bear_token
-                            );
-                            *resp.status_mut() = reqwest::StatusCode::UNAUTHORIZED;
-                            return Ok(resp);
-                        }
-                        Ok(resp)
-                    })
-                    .await
-                    {
-                        Ok(ws_stream) => {
-                            let (write, read) = ws_stream.split();
-
-                            info!(
-                                "Connection succeed, bot_id: {:?}, type: {:?}",
-                                bot_id, r#type
-                            );
-                            return Ok(((read, write), bot_id, r#type));
+            let accepted = tokio::select! {
+                accepted = listener.accept() => accepted,
+                _ = shutdown_signal(&mut shutdown) => break,
+            };
+            match accepted {
+                Ok((stream, addr)) => {
+                    let self_clone = self.clone();
+                    let tls_acceptor = tls_acceptor.clone();
+                    let mut shutdown = shutdown.clone();
+                    connections.retain(|connection| !connection.is_finished());
+                    connections.push(tokio::spawn(async move {
+                        let accepted = tokio::select! {
+                            accepted = self_clone.accept_client(stream, addr, tls_acceptor) => accepted,
+                            _ = shutdown_signal(&mut shutdown) => None,
+                        };
+                        if let Some((client, read)) = accepted {
+                            self_clone.listen(client, read, shutdown).await;
                         }
-                        Err(e) => {
-                            warn!("Connection failed: {}", e);
-                        }
-                    };
+                    }));
                 }
                 Err(e) => {
                     warn!("Connection failed: {}", e);
                 }
             }
         }
+        drop(listener);
+        for connection in connections {
+            if let Err(e) = connection.await {
+                warn!("Error joining connection task: {}", e);
+            }
+        }
+        info!("Reverse WebSocket server shut down");
+    }
+
+    async fn accept_client(
+        &self,
+        stream: TcpStream,
+        addr: SocketAddr,
+        tls_acceptor: Option<TlsAcceptor>,
+    ) -> Option<(Arc<ReverseWsClient>, SplitStream<ServerWsStream>)> {
+        let stream: Box<dyn ServerStream> = match tls_acceptor {
+            Some(acceptor) => match acceptor.accept(stream).await {
+                Ok(stream) => Box::new(stream),
+                Err(e) => {
+                    warn!("TLS handshake failed, addr: {}, error: {}", addr, e);
+                    return None;
+                }
+            },
+            None => Box::new(stream),
+        };
+        let mut bot_id = None;
+        let mut r#type = WsType::Universal;
+        let mut path = String::new();
+        // 握手回调的错误类型 `ErrorResponse` 由 tungstenite 决定
+        #[allow(clippy::result_large_err)]
+        let ws_stream = match accept_hdr_async(stream, |req: &Request, resp: Response| {
+            path = req.uri().path().to_string();
+            let route = if self.config.routes.is_empty() {
+                if !path.trim_end_matches('/').ends_with(&self.config.suffix) {
+                    return Err(error_response(reqwest::StatusCode::NOT_FOUND));
+                }
+                None
+            } else {
+                match self.config.route(&path) {
+                    Some(route) => Some(route),
+                    None => {
+                        warn!("Connection failed: no route for {}, addr: {}", path, addr);
+                        return Err(error_response(reqwest::StatusCode::NOT_FOUND));
+                    }
+                }
+            };
+            let headers = req.headers();
+            bot_id = headers
+                .get("X-Self-ID")
+                .map(|v| v.to_str().unwrap_or("").to_string());
+            if bot_id.as_deref().unwrap_or("").is_empty() {
+                tracing::error!("Connection failed: missing X-Self-ID, addr: {}", addr);
+                return Err(error_response(reqwest::StatusCode::BAD_REQUEST));
+            }
+            if let Some(role) = headers.get("X-Client-Role") {
+                r#type = WsType::from_str(role.to_str().unwrap_or(""));
+            }
+            if let Some(route) = route {
+                if let Some(role) = &route.role {
+                    r#type = role.clone();
+                }
+                if route
+                    .bot_id
+                    .as_ref()
+                    .is_some_and(|expected| Some(expected) != bot_id.as_ref())
+                {
+                    tracing::error!(
+                        "Connection failed: bot_id {:?} not allowed on {}, addr: {}",
+                        bot_id,
+                        path,
+                        addr
+                    );
+                    return Err(error_response(reqwest::StatusCode::FORBIDDEN));
+                }
+            }
+            let placement = self.config.token_placement;
+            let header_token = headers
+                .get(AUTHORIZATION)
+                .filter(|_| placement.header())
+                .and_then(|v| v.to_str().ok()?.strip_prefix("Bearer ").map(str::to_string));
+            let query_token = req
+                .uri()
+                .query()
+                .filter(|_| placement.query())
+                .and_then(query_access_token);
+            let token = header_token.or(query_token);
+            tracing::info!(
+                "Connection accepting: addr: {}, bot_id: {:?}, type: {:?}, token: {}",
+                addr,
+                bot_id,
+                r#type,
+                redact(token.as_deref())
+            );
+            let request = AuthRequest {
+                addr,
+                path: &path,
+                bot_id: bot_id.as_deref().unwrap_or(""),
+                r#type: &r#type,
+                token: token.as_deref(),
+            };
+            match self.authenticator.authenticate(&request) {
+                Ok(()) => Ok(resp),
+                Err(rejection) => {
+                    tracing::error!(
+                        "Connection failed: {}, addr: {}, bot_id: {:?}",
+                        rejection,
+                        addr,
+                        bot_id
+                    );
+                    Err(error_response(match rejection {
+                        AuthRejection::Unauthorized(_) => reqwest::StatusCode::UNAUTHORIZED,
+                        AuthRejection::Forbidden(_) => reqwest::StatusCode::FORBIDDEN,
+                    }))
+                }
+            }
+        })
+        .await
+        {
+            Ok(ws_stream) => ws_stream,
+            Err(e) => {
+                warn!("Connection failed: {}", e);
+                return None;
+            }
+        };
+
+        let (write, read) = ws_stream.split();
+        let client = Arc::new(ReverseWsClient {
+            id: self.next_client_id.fetch_add(1, Ordering::Relaxed),
+            bot_id: bot_id.unwrap_or_default(),
+            r#type,
+            path,
+            ws_write: Mutex::new(write),
+            pending: PendingRequests::default(),
+            last_status: std::sync::Mutex::new(None),
+        });
+        info!(
+            "Connection succeed, addr: {}, bot_id: {}, type: {:?}, path: {}",
+            addr, client.bot_id, client.r#type, client.path
+        );
+        Some((client, read))
     }
 
-    fn start_event_listener(self: Arc<Self>) {
-        let self_clone = Arc::clone(&self);
-
-        tokio::spawn(async move {
-            {
-                let mut read = self.ws_read.lock().await;
-                let sender = self.event_sender.clone();
-
-                while let Some(msg) = read.next().await {
-                    match msg {
-                        Ok(msg) => {
-                            let msg_string = msg.to_string();
-                            match serde_json::from_str::<Event>(&msg_string) {
-                                Ok(event) => match event {
-                                    Event::ApiRespBuilder(api_resp_builder) => {
-                                        if let Err(e) =
-                                            self.api_response_sender.send(api_resp_builder)
-                                        {
-                                            warn!("Error sending ApiRespBuilder: {}", e);
-                                        }
-                                    }
-                                    other => {
-                                        if let Err(e) = sender.send(other) {
-                                            warn!("Error sending Event: {}", e);
-                                        }
-                                    }
-                                },
-                                Err(e) => {
-                                    warn!("Error parsing Event: {}, Raw: {}", e, msg_string);
-                                }
+    /// 登记连接并处理其消息, 连接断开或调用 `shutdown()` 后移除
+    async fn listen(
+        &self,
+        client: Arc<ReverseWsClient>,
+        mut read: SplitStream<ServerWsStream>,
+        mut shutdown: watch::Receiver<bool>,
+    ) {
+        let connected =
+            ConnectionState::Connected(Some(client.bot_id.clone()), client.r#type.clone());
+        {
+            // 持有会话锁时更新状态, 保证与断开时的更新顺序一致
+            let mut sessions = self.sessions.write().await;
+            sessions
+                .entry(client.bot_id.clone())
+                .or_insert_with(|| BotSession {
+                    bot_id: client.bot_id.clone(),
+                    event: None,
+                    api: None,
+                })
+                .attach(&client);
+            self.bot_state_sender(&client.bot_id)
+                .send_replace(connected.clone());
+            self.state_sender.send_replace(connected);
+        }
+        self.session_attached.notify_waiters();
+
+        let mut reason = "WsMessage stream ended".to_string();
+        let mut watchdog = HeartbeatWatchdog::new(self.config.heartbeat.clone());
+        loop {
+            let msg = tokio::select! {
+                msg = read.next() => msg,
+                timeout = watchdog.expired() => {
+                    warn!(
+                        "No heartbeat received in {:?}, closing connection, bot_id: {}",
+                        timeout, client.bot_id
+                    );
+                    reason = format!("Heartbeat timeout after {:?}", timeout);
+                    let close = async { client.ws_write.lock().await.close().await };
+                    let _ = tokio::time::timeout(Duration::from_secs(1), close).await;
+                    break;
+                }
+                _ = shutdown_signal(&mut shutdown) => {
+                    reason = "Connection shut down".to_string();
+                    let close = async { client.ws_write.lock().await.close().await };
+                    let _ = tokio::time::timeout(Duration::from_secs(1), close).await;
+                    break;
+                }
+            };
+            let Some(msg) = msg else {
+                break;
+            };
+            match msg {
+                Ok(msg) => match WsFrame::from(msg) {
+                    WsFrame::Event(Event::ApiRespBuilder(api_resp_builder)) => {
+                        if !client.is_api_capable() {
+                            warn!(
+                                "Ignoring ApiRespBuilder from {:?} connection, bot_id: {}",
+                                client.r#type, client.bot_id
+                            );
+                        } else {
+                            let echo = api_resp_builder.echo.clone();
+                            if !client.pending.resolve(api_resp_builder) {
+                                warn!(
+                                    "Received ApiRespBuilder without pending call, echo: {}",
+                                    echo
+                                );
                             }
                         }
-                        Err(e) => {
-                            warn!("Error receiving WsMessage: {}", e);
+                    }
+                    WsFrame::Event(event) => {
+                        if let Event::Meta(Meta::Heartbeat(heartbeat)) = &event {
+                            if let Some(status) = watchdog.beat(heartbeat) {
+                                *client.last_status.lock().unwrap() = Some(status);
+                            }
                         }
+                        if !client.is_event_capable() {
+                            warn!(
+                                "Ignoring Event from {:?} connection, bot_id: {}",
+                                client.r#type, client.bot_id
+                            );
+                        } else if !self.events.try_send(event) {
+                            // 不暂停读取, 以免阻塞同一连接上的 API 响应
+                            warn!(
+                                "Event queue is full, closing connection, bot_id: {}",
+                                client.bot_id
+                            );
+                            reason = "Event queue is full".to_string();
+                            let close = async { client.ws_write.lock().await.close().await };
+                            let _ = tokio::time::timeout(Duration::from_secs(1), close).await;
+                            break;
+                        }
+                    }
+                    WsFrame::Close(close_reason) => {
+                        warn!("{}, bot_id: {}", close_reason, client.bot_id);
+                        reason = close_reason;
                     }
+                    WsFrame::Skip => {}
+                },
+                Err(e) => {
+                    warn!("Error receiving WsMessage: {}", e);
+                    reason = e.to_string();
                 }
             }
-            warn!("WsMessage stream ended, maybe the connection is closed");
+        }
+        warn!(
+            "WsMessage stream ended, maybe the connection is closed, bot_id: {}, type: {:?}",
+            client.bot_id, client.r#type
+        );
+        client.pending.fail_all(&reason);
+        let mut sessions = self.sessions.write().await;
+        if let Some(session) = sessions.get_mut(&client.bot_id) {
+            session.detach(&client);
+            if session.is_empty() {
+                sessions.remove(&client.bot_id);
+                self.bot_state_sender(&client.bot_id)
+                    .send_replace(ConnectionState::Disconnected(reason.clone()));
+            }
+        }
+        if sessions.is_empty() {
+            self.state_sender
+                .send_replace(ConnectionState::Disconnected(reason));
+        }
+    }
 
-            if let Ok(((read, write), bot_id, r#type)) = Self::connect(&self_clone.config).await {
-                {
-                    *self_clone.ws_read.lock().await = read;
-                    *self_clone.ws_write.lock().await = write;
-                }
-                {
-                    *self_clone.r#bot_id.write().await = bot_id;
-                    *self_clone.r#type.write().await = r#type;
+    /// 关闭服务端: 停止接受新连接, 向所有连接发送关闭帧, 使等待中的调用失败并等待后台任务退出
+    pub async fn shutdown(&self) {
+        self.shutdown.send_replace(true);
+        let task = self.task.lock().unwrap().take();
+        if let Some(task) = task {
+            if let Err(e) = task.await {
+                warn!("Error joining reverse WebSocket task: {}", e);
+            }
+        }
+        self.state_sender.send_replace(ConnectionState::Closed);
+        for state in self.bot_states.lock().unwrap().values() {
+            state.send_replace(ConnectionState::Closed);
+        }
+    }
+
+    pub async fn subscribe(&self) -> EventReceiver {
+        self.events.subscribe()
+    }
+
+    /// 订阅者因处理过慢而丢失的事件总数
+    pub fn dropped_events(&self) -> u64 {
+        self.events.dropped_events()
+    }
+
+    /// 观察服务端的汇总连接状态, 最近一次有连接建立时为 `Connected`, 所有连接断开后为 `Disconnected`.
+    /// 单个机器人的断开请使用 `bot_state` 观察
+    pub fn state(&self) -> watch::Receiver<ConnectionState> {
+        self.state_sender.subscribe()
+    }
+
+    /// 观察指定机器人的连接状态, 该机器人首次连接前为 `Connecting`,
+    /// 其所有连接断开后为 `Disconnected`
+    pub fn bot_state(&self, bot_id: &str) -> watch::Receiver<ConnectionState> {
+        self.bot_state_sender(bot_id).subscribe()
+    }
+
+    fn bot_state_sender(&self, bot_id: &str) -> watch::Sender<ConnectionState> {
+        self.bot_states
+            .lock()
+            .unwrap()
+            .entry(bot_id.to_string())
+            .or_insert_with(|| watch::channel(ConnectionState::Connecting).0)
+            .clone()
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.state_sender.borrow().is_connected()
+    }
+
+    /// 指定机器人最近一次心跳事件中的运行状态
+    pub async fn last_status(&self, bot_id: &str) -> Option<GetStatusResponse> {
+        self.sessions
+            .read()
+            .await
+            .get(bot_id)
+            .and_then(|session| session.event.as_ref())
+            .and_then(|client| client.last_status())
+    }
+
+    /// 当前所有机器人会话, 按机器人 QQ 号排序
+    pub async fn sessions(&self) -> Vec<BotSession> {
+        let mut sessions: Vec<_> = self.sessions.read().await.values().cloned().collect();
+        sessions.sort_by(|a, b| a.bot_id.cmp(&b.bot_id));
+        sessions
+    }
+
+    /// 当前已连接的机器人 QQ 号
+    pub async fn bots(&self) -> Vec<String> {
+        self.sessions()
+            .await
+            .into_iter()
+            .map(|session| session.bot_id)
+            .collect()
+    }
+
+    /// 等待任意机器人的可调用 API 的连接建立, 服务端关闭后返回错误
+    pub async fn wait_connected(&self) -> Result<(), anyhow::Error> {
+        self.wait_for_api_client(None).await
+    }
+
+    /// 等待指定机器人的可调用 API 的连接建立, 服务端关闭后返回错误
+    pub async fn wait_for_bot(&self, bot_id: &str) -> Result<(), anyhow::Error> {
+        self.wait_for_api_client(Some(bot_id)).await
+    }
+
+    async fn wait_for_api_client(&self, bot_id: Option<&str>) -> Result<(), anyhow::Error> {
+        let mut shutdown = self.shutdown.subscribe();
+        loop {
+            // 先注册通知再检查会话, 避免错过检查与等待之间登记的连接
+            let attached = self.session_attached.notified();
+            tokio::pin!(attached);
+            attached.as_mut().enable();
+            if self.api_client(bot_id).await.is_some() {
+                return Ok(());
+            }
+            tokio::select! {
+                _ = attached => {}
+                _ = shutdown_signal(&mut shutdown) => {
+                    return Err(anyhow::anyhow!("Reverse WebSocket server shut down"));
                 }
-                self_clone.start_event_listener();
             }
-        });
+        }
+    }
+
+    /// 选取可用于调用 API 的连接, 指定 `bot_id` 时只选取该机器人的连接
+    async fn api_client(&self, bot_id: Option<&str>) -> Option<Arc<ReverseWsClient>> {
+        match bot_id {
+            Some(bot_id) => self
+                .sessions
+                .read()
+                .await
+                .get(bot_id)
+                .and_then(|session| session.api.clone()),
+            None => self
+                .sessions()
+                .await
+                .into_iter()
+                .find_map(|session| session.api),
+        }
+    }
+
+    /// 通过任意一个可调用 API 的连接调用 API
+    pub async fn call_api(&self, api_data: ApiPayload) -> Result<ApiResp, ApiError> {
+        self.call_api_with(api_data, CallOptions::default()).await
     }
 
-    pub async fn subscribe(&self) -> broadcast::Receiver<Event> {
-        self.event_sender.subscribe()
+    /// 通过指定机器人的连接调用 API
+    pub async fn call_api_with_bot(
+        &self,
+        bot_id: &str,
+        api_data: ApiPayload,
+    ) -> Result<ApiResp, ApiError> {
+        let options = CallOptions {
+            bot_id: Some(bot_id.to_string()),
+            ..Default::default()
+        };
+        self.call_api_with(api_data, options).await
     }
 
-    pub async fn call_api(self: Arc<Self>, api_data: ApiPayload) -> Result<ApiResp, anyhow::Error> {
+    /// 使用指定选项调用 API, 调用方取消（丢弃 future）时会清理等待中的请求
+    pub async fn call_api_with(
+        &self,
+        api_data: ApiPayload,
+        options: CallOptions,
+    ) -> Result<ApiResp, ApiError> {
         let resp_type = api_data.to_resp_type();
-        let ws_api_data: WsApiPayload = api_data.into();
+        self.call_api_raw(api_data, options).await?.build(resp_type)
+    }
+
+    /// 调用 API 并返回未解析 `data` 的响应
+    pub async fn call_api_raw(
+        &self,
+        api_data: ApiPayload,
+        options: CallOptions,
+    ) -> Result<ApiRespBuilder, ApiError> {
+        let client = match &options.bot_id {
+            Some(bot_id) => self.api_client(Some(bot_id)).await.ok_or_else(|| {
+                ApiError::NotSent(anyhow::anyhow!(
+                    "[WsServer.call_api] No API-capable connection for bot {}",
+                    bot_id
+                ))
+            })?,
+            None => self.api_client(None).await.ok_or_else(|| {
+                ApiError::NotSent(anyhow::anyhow!(
+                    "[WsServer.call_api] No API-capable connection"
+                ))
+            })?,
+        };
+        self.call_api_on(&client, api_data, options).await
+    }
+
+    async fn call_api_on(
+        &self,
+        client: &ReverseWsClient,
+        api_data: ApiPayload,
+        options: CallOptions,
+    ) -> Result<ApiRespBuilder, ApiError> {
+        let ws_api_data = WsApiPayload::new(api_data, options.mode);
         let echo = ws_api_data.echo.clone();
-        let ws_api_string: String = serde_json::to_string(&ws_api_data)?;
-        {
-            let mut write = self.ws_write.lock().await;
-            write.send(Message::Text(ws_api_string)).await?;
+        let ws_api_string: String =
+            serde_json::to_string(&ws_api_data).map_err(|e| ApiError::Transport(e.into()))?;
+        let pending_call = client.pending.register(echo);
+        let send = async {
+            let mut write = client.ws_write.lock().await;
+            Ok(write.send(Message::Text(ws_api_string)).await?)
+        };
+        pending_call
+            .send_and_wait(options.timeout.unwrap_or(self.config.api_timeout), send)
+            .await
+    }
+
+    /// 对收到的事件执行快速操作, `context` 为原始事件, 通过收到该事件的机器人的连接调用
+    pub async fn handle_quick_operation(
+        &self,
+        context: Event,
+        operation: QuickOperation,
+    ) -> Result<ApiResp, ApiError> {
+        let bot_id = context.self_id().map(|id| id.to_string());
+        let api_data =
+            ApiPayload::HandleQuickOperation(HandleQuickOperation { context, operation });
+        match bot_id {
+            Some(bot_id) => self.call_api_with_bot(&bot_id, api_data).await,
+            None => self.call_api(api_data).await,
+        }
+    }
+}
+
+/// 从查询字符串中读取 `access_token` 参数
+fn query_access_token(query: &str) -> Option<String> {
+    let mut url = Url::parse("ws://localhost/").ok()?;
+    url.set_query(Some(query));
+    url.query_pairs()
+        .find(|(key, _)| key == "access_token")
+        .map(|(_, value)| value.into_owned())
+}
+
+fn error_response(status: reqwest::StatusCode) -> ErrorResponse {
+    let mut resp = ErrorResponse::new(None);
+    *resp.status_mut() = status;
+    resp
+}
+
+#[async_trait]
+impl OneBotClient for ReverseWsConnect {
+    async fn call_api_raw(
+        &self,
+        api_data: ApiPayload,
+        options: CallOptions,
+    ) -> Result<ApiRespBuilder, ApiError> {
+        ReverseWsConnect::call_api_raw(self, api_data, options).await
+    }
+
+    async fn subscribe(&self) -> Option<EventReceiver> {
+        Some(ReverseWsConnect::subscribe(self).await)
+    }
+
+    fn is_connected(&self) -> bool {
+        ReverseWsConnect::is_connected(self)
+    }
+
+    async fn shutdown(&self) {
+        ReverseWsConnect::shutdown(self).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::payload::{GetStatus, SetGroupCard};
+    use crate::connect::channel::EventChannelMode;
+    use tokio::time::timeout;
+    use tokio_tungstenite::tungstenite::client::IntoClientRequest as _;
+
+    type ClientWsStream = WebSocketStream<tokio_tungstenite::MaybeTlsStream<TcpStream>>;
+
+    async fn server() -> Arc<ReverseWsConnect> {
+        server_with(ReverseWsConfig::default()).await
+    }
+
+    async fn server_with(config: ReverseWsConfig) -> Arc<ReverseWsConnect> {
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        ReverseWsConnect::new(ReverseWsConfig { port, ..config })
+            .await
+            .unwrap()
+    }
+
+    async fn connect_to(
+        server: &ReverseWsConnect,
+        path: &str,
+        headers: &[(&'static str, &str)],
+    ) -> Result<ClientWsStream, tokio_tungstenite::tungstenite::Error> {
+        let mut request = format!("ws://127.0.0.1:{}{}", server.config.port, path)
+            .into_client_request()
+            .unwrap();
+        for (name, value) in headers {
+            request.headers_mut().insert(*name, value.parse().unwrap());
+        }
+        Ok(tokio_tungstenite::connect_async(request).await?.0)
+    }
+
+    async fn connect_bot(server: &ReverseWsConnect, bot_id: &str) -> ClientWsStream {
+        connect_to(server, "/onebot/v11", &[("X-Self-ID", bot_id)])
+            .await
+            .unwrap()
+    }
+
+    fn heartbeat(time: i64) -> Message {
+        let heartbeat = serde_json::json!({
+            "time": time,
+            "self_id": 10001,
+            "post_type": "meta_event",
+            "meta_event_type": "heartbeat",
+            "status": {"online": true, "good": true},
+            "interval": 5000,
+        });
+        Message::Text(heartbeat.to_string())
+    }
+
+    fn heartbeat_time(event: Event) -> i64 {
+        match event {
+            Event::Meta(Meta::Heartbeat(heartbeat)) => heartbeat.time,
+            event => panic!("unexpected event: {:?}", event),
+        }
+    }
+
+    /// 等待会话满足条件, 超时则失败
+    async fn wait_for_session(
+        server: &ReverseWsConnect,
+        bot_id: &str,
+        predicate: impl Fn(Option<&BotSession>) -> bool,
+    ) {
+        timeout(Duration::from_secs(5), async {
+            loop {
+                let sessions = server.sessions().await;
+                if predicate(sessions.iter().find(|session| session.bot_id == bot_id)) {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+    }
+
+    /// 握手被拒绝时返回的 HTTP 状态码
+    fn rejected_status(
+        result: Result<ClientWsStream, tokio_tungstenite::tungstenite::Error>,
+    ) -> u16 {
+        match result {
+            Err(tokio_tungstenite::tungstenite::Error::Http(resp)) => resp.status().as_u16(),
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(_) => panic!("connection should be rejected"),
+        }
+    }
+
+    fn routed_server_config() -> ReverseWsConfig {
+        ReverseWsConfig {
+            routes: vec![
+                ReverseWsRoute {
+                    path: "/onebot/v11/event".to_string(),
+                    role: Some(WsType::Event),
+                    bot_id: None,
+                },
+                ReverseWsRoute {
+                    path: "onebot/v11/api/".to_string(),
+                    role: Some(WsType::Api),
+                    bot_id: Some("10001".to_string()),
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn routes_reject_unknown_paths() {
+        let server = server_with(routed_server_config()).await;
+        // 设置路由表后 `suffix` 不再生效
+        for path in ["/onebot/v11", "/onebot/v11/ws", "/other/onebot/v11/event"] {
+            let result = connect_to(&server, path, &[("X-Self-ID", "10001")]).await;
+            assert_eq!(rejected_status(result), 404, "path: {}", path);
+        }
+        server.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn route_role_overrides_client_role() {
+        let server = server_with(routed_server_config()).await;
+        // 首尾的 `/` 不影响匹配
+        let _event_ws = connect_to(
+            &server,
+            "/onebot/v11/event/",
+            &[("X-Self-ID", "10001"), ("X-Client-Role", "Universal")],
+        )
+        .await
+        .unwrap();
+        let _api_ws = connect_to(
+            &server,
+            "/onebot/v11/api",
+            &[("X-Self-ID", "10001"), ("X-Client-Role", "Event")],
+        )
+        .await
+        .unwrap();
+        wait_for_session(&server, "10001", |session| {
+            session.is_some_and(|session| session.event.is_some() && session.api.is_some())
+        })
+        .await;
+        let session = server
+            .sessions()
+            .await
+            .into_iter()
+            .find(|session| session.bot_id == "10001")
+            .unwrap();
+        assert_eq!(session.event.unwrap().r#type, WsType::Event);
+        assert_eq!(session.api.unwrap().r#type, WsType::Api);
+        server.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn route_bot_id_rejects_other_bots() {
+        let server = server_with(routed_server_config()).await;
+        let result = connect_to(&server, "/onebot/v11/api", &[("X-Self-ID", "10002")]).await;
+        assert_eq!(rejected_status(result), 403);
+        // 未限制账号的路由接受任意机器人
+        let _event_ws = connect_to(&server, "/onebot/v11/event", &[("X-Self-ID", "10002")])
+            .await
+            .unwrap();
+        server.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn event_and_api_sockets_are_paired() {
+        let server = server().await;
+        let mut events = server.subscribe().await;
+        let mut event_ws = connect_to(
+            &server,
+            "/onebot/v11",
+            &[("X-Self-ID", "10001"), ("X-Client-Role", "Event")],
+        )
+        .await
+        .unwrap();
+        let mut api_ws = connect_to(
+            &server,
+            "/onebot/v11",
+            &[("X-Self-ID", "10001"), ("X-Client-Role", "API")],
+        )
+        .await
+        .unwrap();
+        wait_for_session(&server, "10001", |session| {
+            session.is_some_and(|session| session.event.is_some() && session.api.is_some())
+        })
+        .await;
+
+        // API 调用只通过 API 连接发送
+        let call = tokio::spawn({
+            let server = server.clone();
+            async move {
+                server
+                    .call_api_with_bot("10001", ApiPayload::GetStatus(GetStatus {}))
+                    .await
+            }
+        });
+        let Some(Ok(Message::Text(request))) = api_ws.next().await else {
+            panic!("API call not sent over the API connection");
+        };
+        let request: serde_json::Value = serde_json::from_str(&request).unwrap();
+        assert_eq!(request["action"], "get_status");
+        let response = serde_json::json!({
+            "status": "ok",
+            "retcode": 0,
+            "data": {"online": true, "good": true},
+            "echo": request["echo"],
+        });
+        api_ws
+            .send(Message::Text(response.to_string()))
+            .await
+            .unwrap();
+        let resp = call.await.unwrap().unwrap();
+        assert_eq!(resp.retcode, 0);
+        assert!(timeout(Duration::from_millis(100), event_ws.next())
+            .await
+            .is_err());
+
+        // API 连接上报的事件被忽略
+        api_ws.send(heartbeat(1)).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        event_ws.send(heartbeat(2)).await.unwrap();
+        assert_eq!(heartbeat_time(events.recv().await.unwrap()), 2);
+        assert!(timeout(Duration::from_millis(100), events.recv())
+            .await
+            .is_err());
+
+        // 关闭 API 连接后会话只剩 Event 连接
+        api_ws.close(None).await.unwrap();
+        wait_for_session(&server, "10001", |session| {
+            session.is_some_and(|session| session.event.is_some() && session.api.is_none())
+        })
+        .await;
+        assert!(server.bot_state("10001").borrow().is_connected());
+        let err = server
+            .call_api_with_bot("10001", ApiPayload::GetStatus(GetStatus {}))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ApiError::NotSent(_)));
+        server.shutdown().await;
+    }
+
+    #[test]
+    fn query_access_token_decodes_value() {
+        assert_eq!(
+            query_access_token("access_token=abc").as_deref(),
+            Some("abc")
+        );
+        assert_eq!(
+            query_access_token("foo=1&access_token=a%20b%2Bc&bar=2").as_deref(),
+            Some("a b+c")
+        );
+        assert_eq!(query_access_token("token=abc"), None);
+        assert_eq!(query_access_token(""), None);
+    }
+
+    #[tokio::test]
+    async fn query_token_placement() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let server = ReverseWsConnect::new(ReverseWsConfig {
+            port,
+            access_token: Some("secret".to_string()),
+            token_placement: TokenPlacement::Query,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        let connect = |query: &'static str, header: Option<&'static str>| async move {
+            let mut request = format!("ws://127.0.0.1:{}/onebot/v11{}", port, query)
+                .into_client_request()
+                .unwrap();
+            let headers = request.headers_mut();
+            headers.insert("X-Self-ID", "10001".parse().unwrap());
+            if let Some(header) = header {
+                headers.insert(AUTHORIZATION, header.parse().unwrap());
+            }
+            tokio_tungstenite::connect_async(request).await
+        };
+
+        assert!(connect("?access_token=secret", None).await.is_ok());
+        assert!(connect("?access_token=wrong", None).await.is_err());
+        assert!(connect("", Some("Bearer secret")).await.is_err());
+        server.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn wait_for_bot_returns_after_bot_connects() {
+        let server = server().await;
+        let waiting = tokio::spawn({
+            let server = server.clone();
+            async move { server.wait_for_bot("10001").await }
+        });
+        let _other = connect_bot(&server, "10002").await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!waiting.is_finished());
+
+        let _bot = connect_bot(&server, "10001").await;
+        tokio::time::timeout(Duration::from_secs(5), waiting)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        server.wait_connected().await.unwrap();
+        server.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn bot_state_tracks_each_bot() {
+        let server = server().await;
+        let mut first = server.bot_state("10001");
+        let mut second = server.bot_state("10002");
+        assert_eq!(*first.borrow(), ConnectionState::Connecting);
+
+        let first_ws = connect_bot(&server, "10001").await;
+        let _second_ws = connect_bot(&server, "10002").await;
+        first.wait_for(|state| state.is_connected()).await.unwrap();
+        second.wait_for(|state| state.is_connected()).await.unwrap();
+
+        drop(first_ws);
+        tokio::time::timeout(
+            Duration::from_secs(5),
+            first.wait_for(|state| matches!(state, ConnectionState::Disconnected(_))),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert!(second.borrow().is_connected());
+        assert!(server.is_connected());
+
+        server.shutdown().await;
+        assert_eq!(*first.borrow(), ConnectionState::Closed);
+        assert_eq!(*second.borrow(), ConnectionState::Closed);
+    }
+
+    #[tokio::test]
+    async fn heartbeat_timeout_disconnects_bot() {
+        let server = server_with(ReverseWsConfig {
+            heartbeat: Some(HeartbeatConfig { missed_beats: 2 }),
+            ..Default::default()
+        })
+        .await;
+        let mut state = server.bot_state("10001");
+        let mut bot = connect_bot(&server, "10001").await;
+        bot.send(heartbeat(0)).await.unwrap();
+        timeout(Duration::from_secs(5), async {
+            while server.last_status("10001").await.is_none() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(
+            server.last_status("10001").await,
+            Some(GetStatusResponse {
+                online: Some(true),
+                good: true
+            })
+        );
+
+        // 机器人保持连接但不再发送心跳
+        tokio::time::pause();
+        state
+            .wait_for(|state| matches!(state, ConnectionState::Disconnected(_)))
+            .await
+            .unwrap();
+        let ConnectionState::Disconnected(reason) = state.borrow().clone() else {
+            unreachable!();
+        };
+        assert!(reason.contains("Heartbeat timeout after 10s"));
+        server.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn call_timeout_covers_blocked_send() {
+        let server = server().await;
+        let _bot = connect_bot(&server, "10001").await;
+        server.wait_for_bot("10001").await.unwrap();
+        let options = CallOptions {
+            timeout: Some(Duration::from_millis(500)),
+            ..Default::default()
+        };
+        // 机器人不读取, 足以填满套接字缓冲区的请求会一直阻塞发送
+        let large = ApiPayload::SetGroupCard(SetGroupCard {
+            group_id: 1,
+            user_id: 1,
+            card: "x".repeat(32 * 1024 * 1024),
+        });
+        for api_data in [large, ApiPayload::GetStatus(GetStatus {})] {
+            let start = tokio::time::Instant::now();
+            let result = timeout(
+                Duration::from_secs(5),
+                server.call_api_raw(api_data, options.clone()),
+            )
+            .await
+            .expect("call should time out on its own");
+            assert!(matches!(result, Err(ApiError::Timeout(_))));
+            assert!(start.elapsed() < Duration::from_secs(3));
+        }
+        server.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn shutdown_with_unread_lossless_events() {
+        let server = server_with(ReverseWsConfig {
+            events: EventChannelConfig {
+                capacity: 4,
+                mode: EventChannelMode::Lossless,
+            },
+            ..Default::default()
+        })
+        .await;
+        let _subscriber = server.subscribe().await;
+        let mut bot = connect_bot(&server, "10001").await;
+        // 队列满后连接会被断开
+        for time in 0..1000 {
+            if bot.send(heartbeat(time)).await.is_err() {
+                break;
+            }
         }
-        let subscriber = self.api_response_sender.subscribe();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        tokio::time::timeout(Duration::from_secs(5), server.shutdown())
+            .await
+            .expect("shutdown should not wait for the subscriber");
+    }
 
-        let resp_builder = timeout(
-            Duration::from_secs(30),
-            get_resp_builder(subscriber, echo)
+    #[tokio::test]
+    async fn lossless_overflow_disconnects_bot() {
+        let server = server_with(ReverseWsConfig {
+            events: EventChannelConfig {
+                capacity: 4,
+                mode: EventChannelMode::Lossless,
+            },
+            ..Default::default()
+        })
+        .await;
+        let mut subscriber = server.subscribe().await;
+        let mut state = server.bot_state("10001");
+        let mut bot = connect_bot(&server, "10001").await;
+        for time in 0..5 {
+            bot.send(heartbeat(time)).await.unwrap();
+        }
+        timeout(
+            Duration::from_secs(5),
+            state.wait_for(|state| matches!(state, ConnectionState::Disconnected(_))),
         )
         .await
-        .ok()
-        .flatten()
-        .ok_or(anyhow::anyhow!(
-            "[WsServer.call_api] Error receiving API response, maybe the API response channel is closed or timeout"
-        ))?;
-        Ok(resp_builder.build(resp_type)?)
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            *state.borrow(),
+            ConnectionState::Disconnected("Event queue is full".to_string())
+        );
+        assert_eq!(server.dropped_events(), 1);
+        for time in 0..4 {
+            assert_eq!(heartbeat_time(subscriber.recv().await.unwrap()), time);
+        }
+        server.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn wait_connected_fails_after_shutdown() {
+        let server = server().await;
+        let waiting = tokio::spawn({
+            let server = server.clone();
+            async move { server.wait_connected().await }
+        });
+        server.shutdown().await;
+        let result = tokio::time::timeout(Duration::from_secs(5), waiting)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(result.is_err());
     }
 }
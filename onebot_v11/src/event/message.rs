@@ -8,6 +8,16 @@ pub enum Message {
     GroupMessage(GroupMessage),
 }
 
+impl Message {
+    /// 收到事件的机器人 QQ 号
+    pub fn self_id(&self) -> i64 {
+        match self {
+            Message::PrivateMessage(m) => m.self_id,
+            Message::GroupMessage(m) => m.self_id,
+        }
+    }
+}
+
 impl Serialize for Message {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -173,31 +183,38 @@ pub struct Anonymous {
     pub flag: String,
 }
 
-// 以下暂且不用
-
 /// 私聊消息的快速操作结构体
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
 pub struct PrivateMessageQuickOperation {
     /// 要回复的内容
-    pub reply: Vec<MessageSegment>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reply: Option<Vec<MessageSegment>>,
     /// 消息内容是否作为纯文本发送
-    pub auto_escape: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_escape: Option<bool>,
 }
 /// 群消息的快速操作结构体
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
 pub struct GroupMessageQuickOperation {
     /// 要回复的内容
-    pub reply: Vec<MessageSegment>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reply: Option<Vec<MessageSegment>>,
     /// 消息内容是否作为纯文本发送
-    pub auto_escape: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_escape: Option<bool>,
     /// 是否要在回复开头 at 发送者
-    pub at_sender: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub at_sender: Option<bool>,
     /// 撤回该条消息
-    pub delete: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delete: Option<bool>,
     /// 把发送者踢出群组
-    pub kick: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kick: Option<bool>,
     /// 把发送者禁言
-    pub ban: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ban: Option<bool>,
     /// 禁言时长
-    pub ban_duration: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ban_duration: Option<i64>,
 }
@@ -7,6 +7,16 @@ pub enum Meta {
     Heartbeat(Heartbeat),
 }
 
+impl Meta {
+    /// 收到事件的机器人 QQ 号
+    pub fn self_id(&self) -> i64 {
+        match self {
+            Meta::Lifecycle(m) => m.self_id,
+            Meta::Heartbeat(m) => m.self_id,
+        }
+    }
+}
+
 impl Serialize for Meta {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
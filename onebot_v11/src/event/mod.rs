@@ -1,5 +1,7 @@
 use crate::api::resp::ApiRespBuilder;
 
+use self::message::{GroupMessageQuickOperation, PrivateMessageQuickOperation};
+use self::request::{FriendRequestQuickOperation, GroupRequestQuickOperation};
 use self::{message::Message, meta::Meta, notice::Notice, request::Request};
 use serde::de::Error;
 use serde::{Deserialize, Serialize};
@@ -10,6 +12,7 @@ pub mod meta;
 pub mod notice;
 pub mod request;
 
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Event {
     Message(Message),
@@ -33,6 +36,42 @@ impl Serialize for Event {
     }
 }
 
+impl Event {
+    /// 收到事件的机器人 QQ 号, API 响应没有此字段
+    pub fn self_id(&self) -> Option<i64> {
+        match self {
+            Event::Message(m) => Some(m.self_id()),
+            Event::Meta(m) => Some(m.self_id()),
+            Event::Notice(m) => Some(m.self_id()),
+            Event::Request(m) => Some(m.self_id()),
+            Event::ApiRespBuilder(_) => None,
+        }
+    }
+}
+
+/// 事件的快速操作，与触发它的事件类型对应
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuickOperation {
+    PrivateMessage(PrivateMessageQuickOperation),
+    GroupMessage(GroupMessageQuickOperation),
+    FriendRequest(FriendRequestQuickOperation),
+    GroupRequest(GroupRequestQuickOperation),
+}
+
+impl Serialize for QuickOperation {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        match self {
+            QuickOperation::PrivateMessage(m) => m.serialize(serializer),
+            QuickOperation::GroupMessage(m) => m.serialize(serializer),
+            QuickOperation::FriendRequest(m) => m.serialize(serializer),
+            QuickOperation::GroupRequest(m) => m.serialize(serializer),
+        }
+    }
+}
+
 impl<'de> Deserialize<'de> for Event {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
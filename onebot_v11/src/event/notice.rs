@@ -34,6 +34,29 @@ pub enum Notice {
     /// 群名片变更事件
     GroupCardChange(GroupCardChangeEvent),
 }
+
+impl Notice {
+    /// 收到事件的机器人 QQ 号
+    pub fn self_id(&self) -> i64 {
+        match self {
+            Notice::GroupFileUpload(n) => n.self_id,
+            Notice::GroupAdminChange(n) => n.self_id,
+            Notice::GroupMemberDecrease(n) => n.self_id,
+            Notice::GroupMemberIncrease(n) => n.self_id,
+            Notice::GroupBan(n) => n.self_id,
+            Notice::FriendAdd(n) => n.self_id,
+            Notice::GroupMessageRecall(n) => n.self_id,
+            Notice::FriendMessageRecall(n) => n.self_id,
+            Notice::GroupPoke(n) => n.self_id,
+            Notice::GroupLuckyKing(n) => n.self_id,
+            Notice::GroupMemberHonorChange(n) => n.self_id,
+            Notice::FriendInputStatusChange(n) => n.self_id,
+            Notice::GroupEssenceMessageChange(n) => n.self_id,
+            Notice::GroupCardChange(n) => n.self_id,
+        }
+    }
+}
+
 impl<'de> Deserialize<'de> for Notice {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
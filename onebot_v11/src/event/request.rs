@@ -7,6 +7,16 @@ pub enum Request {
     GroupRequestEvent(GroupRequestEvent),
 }
 
+impl Request {
+    /// 收到事件的机器人 QQ 号
+    pub fn self_id(&self) -> i64 {
+        match self {
+            Request::FriendRequestEvent(m) => m.self_id,
+            Request::GroupRequestEvent(m) => m.self_id,
+        }
+    }
+}
+
 impl Serialize for Request {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -79,3 +89,25 @@ pub struct GroupRequestEvent {
     pub comment: String,      // 验证信息
     pub flag: String,         // 请求 flag，在调用处理请求的 API 时需要传入
 }
+
+/// 加好友请求的快速操作结构体
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct FriendRequestQuickOperation {
+    /// 是否同意请求
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub approve: Option<bool>,
+    /// 添加后的好友备注（仅在同意时有效）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remark: Option<String>,
+}
+
+/// 加群请求／邀请的快速操作结构体
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct GroupRequestQuickOperation {
+    /// 是否同意请求／邀请
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub approve: Option<bool>,
+    /// 拒绝理由（仅在拒绝时有效）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
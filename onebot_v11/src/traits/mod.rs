@@ -1,3 +1,155 @@
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+
+use crate::api::error::ApiError;
+use crate::api::payload::ApiPayload;
+use crate::api::resp::{Accepted, ApiResp, ApiRespBuilder};
+use crate::connect::channel::EventReceiver;
+use crate::connect::{CallMode, CallOptions};
+
 pub trait EndPoint {
     fn endpoint(&self) -> String;
 }
+
+/// 带有确定响应类型的 API 请求, 由 `ApiPayload` 中的每个请求结构体实现
+pub trait ApiRequest: Into<ApiPayload> {
+    /// 响应中 `data` 字段的类型
+    type Response: DeserializeOwned + Send;
+}
+
+/// OneBot 客户端, 由 http, 正向 ws 与反向 ws 连接共同实现,
+/// 基于该 trait 编写的逻辑可以在任意连接方式上运行
+#[async_trait]
+pub trait OneBotClient: Send + Sync {
+    /// 调用 API 并返回未解析 `data` 的响应
+    async fn call_api_raw(
+        &self,
+        api_data: ApiPayload,
+        options: CallOptions,
+    ) -> Result<ApiRespBuilder, ApiError>;
+
+    /// 使用指定选项调用 API
+    async fn call_api_with(
+        &self,
+        api_data: ApiPayload,
+        options: CallOptions,
+    ) -> Result<ApiResp, ApiError> {
+        let resp_type = api_data.to_resp_type();
+        self.call_api_raw(api_data, options).await?.build(resp_type)
+    }
+
+    async fn call_api(&self, api_data: ApiPayload) -> Result<ApiResp, ApiError> {
+        self.call_api_with(api_data, CallOptions::default()).await
+    }
+
+    /// 连接当前是否可用, 无连接状态的 http 始终返回 `true`
+    fn is_connected(&self) -> bool {
+        true
+    }
+
+    /// 订阅事件, 不支持接收事件的连接返回 `None`
+    async fn subscribe(&self) -> Option<EventReceiver> {
+        None
+    }
+
+    /// 关闭连接并等待后台任务退出, 之后的调用均会失败
+    async fn shutdown(&self) {}
+}
+
+/// 以请求类型决定响应类型的 API 调用, 如 `client.call(GetGroupInfo { .. })` 返回 `GetGroupInfoResponse`,
+/// 对所有 `OneBotClient` 自动实现
+#[async_trait]
+pub trait OneBotClientExt: OneBotClient {
+    async fn call<R: ApiRequest + Send>(&self, request: R) -> Result<R::Response, ApiError> {
+        self.call_with(request, CallOptions::default()).await
+    }
+
+    /// 使用指定选项调用并解析响应, `options.mode` 应为 `CallMode::Normal`,
+    /// 异步与限速调用的响应不含 `data`, 请使用 `call_async` / `call_rate_limited`
+    async fn call_with<R: ApiRequest + Send>(
+        &self,
+        request: R,
+        options: CallOptions,
+    ) -> Result<R::Response, ApiError> {
+        self.call_api_raw(request.into(), options).await?.decode()
+    }
+
+    /// 以 `_async` 方式调用, OneBot 实现接受请求后立即返回, 不等待操作结果
+    async fn call_async<R: ApiRequest + Send>(&self, request: R) -> Result<Accepted, ApiError> {
+        let options = CallOptions {
+            mode: CallMode::Async,
+            ..Default::default()
+        };
+        self.call_api_raw(request.into(), options).await?.accepted()
+    }
+
+    /// 以 `_rate_limited` 方式调用, OneBot 实现排队限速执行, 接受请求后立即返回
+    async fn call_rate_limited<R: ApiRequest + Send>(
+        &self,
+        request: R,
+    ) -> Result<Accepted, ApiError> {
+        let options = CallOptions {
+            mode: CallMode::RateLimited,
+            ..Default::default()
+        };
+        self.call_api_raw(request.into(), options).await?.accepted()
+    }
+}
+
+impl<T: OneBotClient + ?Sized> OneBotClientExt for T {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::payload::SendPrivateMsg;
+    use std::sync::Mutex;
+
+    /// 记录调用方式并按 `_async` / `_rate_limited` 语义响应
+    #[derive(Default)]
+    struct AcceptingClient {
+        modes: Mutex<Vec<CallMode>>,
+    }
+
+    #[async_trait]
+    impl OneBotClient for AcceptingClient {
+        async fn call_api_raw(
+            &self,
+            _api_data: ApiPayload,
+            options: CallOptions,
+        ) -> Result<ApiRespBuilder, ApiError> {
+            self.modes.lock().unwrap().push(options.mode);
+            Ok(serde_json::from_value(serde_json::json!({
+                "status": "async",
+                "retcode": 1,
+                "data": null,
+                "echo": "1",
+            }))
+            .unwrap())
+        }
+    }
+
+    fn request() -> SendPrivateMsg {
+        SendPrivateMsg {
+            user_id: 10001,
+            message: Vec::new(),
+            auto_escape: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn async_and_rate_limited_calls_return_accepted() {
+        let client = AcceptingClient::default();
+        assert_eq!(
+            client.call_async(request()).await.unwrap(),
+            Accepted { retcode: 1 }
+        );
+        assert_eq!(
+            client.call_rate_limited(request()).await.unwrap(),
+            Accepted { retcode: 1 }
+        );
+        assert_eq!(
+            *client.modes.lock().unwrap(),
+            [CallMode::Async, CallMode::RateLimited]
+        );
+    }
+}